@@ -84,6 +84,8 @@ impl ChannelMixer {
             },
             frame.timestamp,
         )
+        .with_capture_time(frame.capture_time)
+        .with_talk_spurt(frame.talk_spurt)
     }
 }
 
@@ -49,6 +49,8 @@ where
             },
             src.timestamp,
         )
+        .with_capture_time(src.capture_time)
+        .with_talk_spurt(src.talk_spurt)
     }
 }
 
@@ -13,6 +13,8 @@ pub(crate) struct RateConverter {
     dst_rate: SampleRate,
 
     timestamp: u64,
+    /// Talk spurt flag of a buffered frame, to be set on the next output frame
+    talk_spurt: bool,
 
     output_buffers: Vec<Vec<f32>>,
 }
@@ -47,11 +49,14 @@ impl RateConverter {
             channels,
             dst_rate: dst,
             timestamp: 0,
+            talk_spurt: false,
             output_buffers,
         }
     }
 
     pub(crate) fn convert(&mut self, src: Frame<RawAudio>) -> Option<Frame<RawAudio>> {
+        self.talk_spurt |= src.talk_spurt;
+
         match_samples!((&src.data().samples) => (samples) => self.queue.extend(samples.iter().map(|s| s.to_sample::<f32>())));
 
         let channel_count = self.resampler.nbr_channels();
@@ -84,14 +89,18 @@ impl RateConverter {
         let timestamp = self.timestamp;
         self.timestamp += (samples_out.len() / channel_count) as u64;
 
-        Some(Frame::new(
-            RawAudioFrame {
-                sample_rate: self.dst_rate,
-                channels: self.channels.clone(),
-                samples: samples_out,
-            },
-            timestamp,
-        ))
+        Some(
+            Frame::new(
+                RawAudioFrame {
+                    sample_rate: self.dst_rate,
+                    channels: self.channels.clone(),
+                    samples: samples_out,
+                },
+                timestamp,
+            )
+            .with_capture_time(src.capture_time)
+            .with_talk_spurt(std::mem::take(&mut self.talk_spurt)),
+        )
     }
 }
 
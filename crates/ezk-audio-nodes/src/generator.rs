@@ -3,7 +3,7 @@ use ezk::{ConfigRange, Frame, NextEventIsCancelSafe, Result, Source, SourceEvent
 use ezk_audio::{
     match_format, RawAudio, RawAudioConfig, RawAudioConfigRange, RawAudioFrame, Sample, Samples,
 };
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::time::{interval, Interval};
 
 pub struct WaveFormGenerator {
@@ -72,7 +72,7 @@ impl Source for WaveFormGenerator {
             samples,
         };
 
-        let frame = Frame::new(frame, self.timestamp);
+        let frame = Frame::new(frame, self.timestamp).with_capture_time(Some(Instant::now()));
 
         self.timestamp += (samples_len / config.channels.channel_count()) as u64;
 
@@ -2,6 +2,7 @@ mod amplify;
 mod convert;
 mod generator;
 mod mixer;
+mod vad;
 
 #[cfg(feature = "nnnoiseless")]
 mod noisefilter;
@@ -10,6 +11,7 @@ pub use amplify::Amplify;
 pub use convert::AudioConvert;
 pub use generator::WaveFormGenerator;
 pub use mixer::AudioMixer;
+pub use vad::{SilenceMode, VoiceActivityDetector};
 
 #[cfg(feature = "nnnoiseless")]
 pub use noisefilter::NoiseFilter;
@@ -27,10 +27,7 @@ struct Stream {
 impl AudioMixer {
     pub fn new(source: impl Source<MediaType = RawAudio> + NextEventIsCancelSafe) -> Self {
         Self {
-            sources: vec![SourceEntry {
-                source: source.boxed(),
-                queue: None,
-            }],
+            sources: vec![SourceEntry::new(source.boxed())],
             stream: None,
             eos_on_empty_sources: true,
         }
@@ -53,10 +50,7 @@ impl AudioMixer {
         &mut self,
         source: impl Source<MediaType = RawAudio> + NextEventIsCancelSafe,
     ) -> &mut Self {
-        self.sources.push(SourceEntry {
-            source: source.boxed(),
-            queue: None,
-        });
+        self.sources.push(SourceEntry::new(source.boxed()));
         self.stream = None;
         self
     }
@@ -247,16 +241,51 @@ fn add(mut a: Frame<RawAudio>, b: Frame<RawAudio>) -> Frame<RawAudio> {
 
     match_samples!((&mut a_data.samples, &b_data.samples) => (a, b) => _add::<#S>(a, b));
 
+    // Keep the earliest capture time, to not hide the latency of any mixed source
+    a.capture_time = a.capture_time.into_iter().chain(b.capture_time).min();
+    a.talk_spurt |= b.talk_spurt;
+
     a
 }
 
 struct SourceEntry {
     source: BoxedSource<RawAudio>,
     queue: Option<SamplesQueue>,
+
+    /// Earliest capture time of the samples in the queue
+    capture_time: Option<Instant>,
+    /// Capture time of the last frame added to the queue
+    last_capture_time: Option<Instant>,
+    /// A frame added to the queue started a talk spurt, set on the next frame taken from the queue
+    talk_spurt: bool,
 }
 
 impl SourceEntry {
+    fn new(source: BoxedSource<RawAudio>) -> Self {
+        Self {
+            source,
+            queue: None,
+            capture_time: None,
+            last_capture_time: None,
+            talk_spurt: false,
+        }
+    }
+
+    fn reset_queue(&mut self) {
+        self.queue = None;
+        self.capture_time = None;
+        self.last_capture_time = None;
+        self.talk_spurt = false;
+    }
+
     fn make_frame(&mut self, config: &RawAudioConfig, samples: Samples) -> Frame<RawAudio> {
+        // Samples left in the queue belong to the last frame added to it
+        let capture_time = if self.queue.as_ref().is_some_and(|queue| !queue.is_empty()) {
+            std::mem::replace(&mut self.capture_time, self.last_capture_time)
+        } else {
+            self.capture_time.take()
+        };
+
         Frame::new(
             RawAudioFrame {
                 sample_rate: config.sample_rate,
@@ -266,6 +295,8 @@ impl SourceEntry {
             // This is set later
             0,
         )
+        .with_capture_time(capture_time)
+        .with_talk_spurt(std::mem::take(&mut self.talk_spurt))
     }
 
     async fn next_event(
@@ -301,6 +332,14 @@ impl SourceEntry {
 
                     queue.extend(&frame.data().samples);
 
+                    self.capture_time = self
+                        .capture_time
+                        .into_iter()
+                        .chain(frame.capture_time)
+                        .min();
+                    self.last_capture_time = frame.capture_time;
+                    self.talk_spurt |= frame.talk_spurt;
+
                     if let Some(samples) = queue.pop_exact(expected_samples_len) {
                         return Ok(Some(SourceEvent::Frame(self.make_frame(config, samples))));
                     } else {
@@ -310,14 +349,114 @@ impl SourceEntry {
 
                 // TODO: Drain the queue before to not lose any data?
                 SourceEvent::EndOfData => {
-                    self.queue = None;
+                    self.reset_queue();
                     return Ok(Some(SourceEvent::EndOfData));
                 }
                 SourceEvent::RenegotiationNeeded => {
-                    self.queue = None;
+                    self.reset_queue();
                     return Ok(Some(SourceEvent::RenegotiationNeeded));
                 }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    struct TestSource {
+        frames: VecDeque<Frame<RawAudio>>,
+    }
+
+    impl NextEventIsCancelSafe for TestSource {}
+
+    impl TestSource {
+        /// Create a source of 8kHz mono frames with 100 samples each, with the given capture times and talk spurt
+        /// flags
+        fn new(frames: impl IntoIterator<Item = (Instant, bool)>) -> Self {
+            let frames = frames
+                .into_iter()
+                .enumerate()
+                .map(|(i, (capture_time, talk_spurt))| {
+                    Frame::new(
+                        RawAudioFrame {
+                            sample_rate: ezk_audio::SampleRate(8000),
+                            channels: ezk_audio::Channels::NotPositioned(1),
+                            samples: Samples::from(vec![0i16; 100]),
+                        },
+                        i as u64 * 100,
+                    )
+                    .with_capture_time(Some(capture_time))
+                    .with_talk_spurt(talk_spurt)
+                })
+                .collect();
+
+            Self { frames }
+        }
+    }
+
+    impl Source for TestSource {
+        type MediaType = RawAudio;
+
+        async fn capabilities(&mut self) -> Result<Vec<RawAudioConfigRange>> {
+            Ok(vec![RawAudioConfigRange {
+                sample_rate: ValueRange::Value(ezk_audio::SampleRate(8000)),
+                channels: ValueRange::Value(ezk_audio::Channels::NotPositioned(1)),
+                format: ValueRange::Value(ezk_audio::Format::I16),
+            }])
+        }
+
+        async fn negotiate_config(
+            &mut self,
+            mut available: Vec<RawAudioConfigRange>,
+        ) -> Result<RawAudioConfig> {
+            let range = available.remove(0);
+
+            Ok(RawAudioConfig {
+                sample_rate: range.sample_rate.first_value(),
+                channels: range.channels.first_value(),
+                format: range.format.first_value(),
+            })
+        }
+
+        async fn next_event(&mut self) -> Result<SourceEvent<RawAudio>> {
+            match self.frames.pop_front() {
+                Some(frame) => Ok(SourceEvent::Frame(frame)),
+                None => Ok(SourceEvent::EndOfData),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn rechunked_frames_keep_capture_time_and_talk_spurt() {
+        let start = Instant::now();
+        let capture_times: Vec<Instant> = (0..4)
+            .map(|i| start + Duration::from_micros(12_500) * i)
+            .collect();
+        let talk_spurts = [true, false, true, false];
+
+        let mut mixer = AudioMixer::new(TestSource::new(
+            capture_times.iter().copied().zip(talk_spurts),
+        ));
+        let available = mixer.capabilities().await.unwrap();
+        mixer.negotiate_config(available).await.unwrap();
+
+        let mut frames = vec![];
+        while let SourceEvent::Frame(frame) = mixer.next_event().await.unwrap() {
+            frames.push(frame);
+        }
+
+        // 400 samples make two 20ms frames, the first one contains samples of the first two input frames and the
+        // second one the remainder of the second input frame. Silence follows once the source ended.
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[0].capture_time, Some(capture_times[0]));
+        assert_eq!(frames[1].capture_time, Some(capture_times[1]));
+        assert_eq!(frames[2].capture_time, None);
+
+        // The talk spurt flag is kept until the samples of the flagged frame are passed on
+        let talk_spurts: Vec<bool> = frames.iter().map(|frame| frame.talk_spurt).collect();
+        assert_eq!(talk_spurts, [true, true, false]);
+    }
+}
@@ -4,6 +4,7 @@ use ezk_audio::{
     Samples, SamplesQueue,
 };
 use nnnoiseless::DenoiseState;
+use std::time::Instant;
 
 pub struct NoiseFilter<S> {
     source: S,
@@ -14,6 +15,13 @@ struct Stream {
     first: bool,
     queue: SamplesQueue,
     state: Box<DenoiseState<'static>>,
+
+    /// Earliest capture time of the samples in the queue
+    capture_time: Option<Instant>,
+    /// Capture time of the last frame added to the queue
+    last_capture_time: Option<Instant>,
+    /// A frame added to the queue started a talk spurt, set on the next frame taken from the queue
+    talk_spurt: bool,
 }
 
 impl<S: Source<MediaType = RawAudio> + NextEventIsCancelSafe> NextEventIsCancelSafe
@@ -71,6 +79,9 @@ impl<S: Source<MediaType = RawAudio>> Source for NoiseFilter<S> {
             first: true,
             queue: SamplesQueue::empty(Format::I16),
             state: DenoiseState::new(),
+            capture_time: None,
+            last_capture_time: None,
+            talk_spurt: false,
         });
 
         self.source
@@ -104,21 +115,40 @@ impl<S: Source<MediaType = RawAudio>> Source for NoiseFilter<S> {
                     continue;
                 }
 
-                return Ok(SourceEvent::Frame(Frame::new(
-                    RawAudioFrame {
-                        sample_rate: SampleRate(48000),
-                        channels: Channels::NotPositioned(1),
-                        samples: Samples::from(Vec::from_iter(
-                            output.into_iter().map(|i| i as i16),
-                        )),
-                    },
-                    0,
-                )));
+                // Samples left in the queue belong to the last frame added to it
+                let capture_time = if stream.queue.is_empty() {
+                    stream.capture_time.take()
+                } else {
+                    std::mem::replace(&mut stream.capture_time, stream.last_capture_time)
+                };
+
+                return Ok(SourceEvent::Frame(
+                    Frame::new(
+                        RawAudioFrame {
+                            sample_rate: SampleRate(48000),
+                            channels: Channels::NotPositioned(1),
+                            samples: Samples::from(Vec::from_iter(
+                                output.into_iter().map(|i| i as i16),
+                            )),
+                        },
+                        0,
+                    )
+                    .with_capture_time(capture_time)
+                    .with_talk_spurt(std::mem::take(&mut stream.talk_spurt)),
+                ));
             }
 
             match self.source.next_event().await? {
                 SourceEvent::Frame(frame) => {
                     stream.queue.extend(&frame.data().samples);
+
+                    stream.capture_time = stream
+                        .capture_time
+                        .into_iter()
+                        .chain(frame.capture_time)
+                        .min();
+                    stream.last_capture_time = frame.capture_time;
+                    stream.talk_spurt |= frame.talk_spurt;
                 }
                 SourceEvent::EndOfData => return Ok(SourceEvent::EndOfData),
                 SourceEvent::RenegotiationNeeded => return Ok(SourceEvent::RenegotiationNeeded),
@@ -0,0 +1,369 @@
+use ezk::{Frame, NextEventIsCancelSafe, Result, Source, SourceEvent};
+use ezk_audio::{
+    match_samples, RawAudio, RawAudioConfig, RawAudioConfigRange, RawAudioFrame, Sample, Samples,
+};
+
+const DEFAULT_THRESHOLD_DBFS: f32 = -45.0;
+const DEFAULT_HANGOVER: usize = 10;
+
+/// Defines what the [`VoiceActivityDetector`] does with frames classified as silence
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SilenceMode {
+    /// Forward silent frames unchanged
+    Forward,
+
+    /// Drop silent frames (discontinuous transmission).
+    ///
+    /// The first frame after the silence is marked as the start of a [talk spurt](Frame::talk_spurt), which is
+    /// signaled using the RTP marker bit by a packetizer using the talk spurt marker bit policy.
+    Drop,
+
+    /// Replace silent frames with comfort noise at the level of the background noise.
+    ///
+    /// Like with [`SilenceMode::Drop`], the first frame after the silence is marked as the start of a
+    /// [talk spurt](Frame::talk_spurt).
+    ComfortNoise,
+}
+
+/// Energy based voice activity detector
+///
+/// Frames below the threshold level are classified as silence, after the configured hangover.
+pub struct VoiceActivityDetector<S> {
+    source: S,
+
+    threshold_dbfs: f32,
+    hangover: usize,
+    silence_mode: SilenceMode,
+
+    active: bool,
+    /// Remaining frames to classify as speech after the level dropped below the threshold
+    hangover_remaining: usize,
+    /// Silence has been dropped or replaced since the last frame classified as speech
+    silence_replaced: bool,
+    /// Smoothed RMS level of the silent frames, in the range `0.0..=1.0`
+    noise_level: f32,
+    noise_state: u32,
+}
+
+impl<S: Source<MediaType = RawAudio> + NextEventIsCancelSafe> NextEventIsCancelSafe
+    for VoiceActivityDetector<S>
+{
+}
+
+impl<S: Source<MediaType = RawAudio>> VoiceActivityDetector<S> {
+    pub fn new(source: S) -> Self {
+        Self {
+            source,
+            threshold_dbfs: DEFAULT_THRESHOLD_DBFS,
+            hangover: DEFAULT_HANGOVER,
+            silence_mode: SilenceMode::Forward,
+            active: false,
+            hangover_remaining: 0,
+            silence_replaced: false,
+            noise_level: 0.0,
+            noise_state: 0x9E37_79B9,
+        }
+    }
+
+    /// Level in dBFS above which a frame is classified as speech, defaults to -45 dBFS
+    pub fn with_threshold(mut self, threshold_dbfs: f32) -> Self {
+        self.threshold_dbfs = threshold_dbfs;
+        self
+    }
+
+    /// Number of frames to keep classifying as speech after the level dropped below the threshold, defaults to 10.
+    ///
+    /// This avoids cutting off the quiet end of words.
+    pub fn with_hangover(mut self, frames: usize) -> Self {
+        self.hangover = frames;
+        self
+    }
+
+    /// Set what to do with silent frames, defaults to [`SilenceMode::Forward`]
+    pub fn with_silence_mode(mut self, silence_mode: SilenceMode) -> Self {
+        self.silence_mode = silence_mode;
+        self
+    }
+
+    /// Returns if the last frame was classified as speech
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    fn classify(&mut self, level: f32) {
+        let level_dbfs = 20.0 * level.max(f32::MIN_POSITIVE).log10();
+
+        if level_dbfs >= self.threshold_dbfs {
+            self.active = true;
+            self.hangover_remaining = self.hangover;
+        } else if self.hangover_remaining > 0 {
+            self.hangover_remaining -= 1;
+        } else {
+            self.active = false;
+            self.noise_level = self.noise_level * 0.9 + level * 0.1;
+        }
+    }
+
+    fn make_comfort_noise(&mut self, frame: &Frame<RawAudio>) -> Frame<RawAudio> {
+        let data = frame.data();
+
+        let noise_level = self.noise_level;
+        let state = &mut self.noise_state;
+
+        let samples = match_samples!((&data.samples) => (samples) => comfort_noise::<#S>(samples.len(), noise_level, state));
+
+        Frame::new(
+            RawAudioFrame {
+                sample_rate: data.sample_rate,
+                channels: data.channels.clone(),
+                samples,
+            },
+            frame.timestamp,
+        )
+        .with_capture_time(frame.capture_time)
+    }
+}
+
+impl<S: Source<MediaType = RawAudio>> Source for VoiceActivityDetector<S> {
+    type MediaType = RawAudio;
+
+    async fn capabilities(&mut self) -> Result<Vec<RawAudioConfigRange>> {
+        self.source.capabilities().await
+    }
+
+    async fn negotiate_config(
+        &mut self,
+        available: Vec<RawAudioConfigRange>,
+    ) -> Result<RawAudioConfig> {
+        self.source.negotiate_config(available).await
+    }
+
+    async fn next_event(&mut self) -> Result<SourceEvent<Self::MediaType>> {
+        loop {
+            let mut frame = match self.source.next_event().await? {
+                SourceEvent::Frame(frame) => frame,
+                SourceEvent::EndOfData => return Ok(SourceEvent::EndOfData),
+                SourceEvent::RenegotiationNeeded => return Ok(SourceEvent::RenegotiationNeeded),
+            };
+
+            let level = match_samples!((&frame.data().samples) => (samples) => rms::<#S>(samples));
+            self.classify(level);
+
+            if self.active {
+                if std::mem::take(&mut self.silence_replaced) {
+                    frame.talk_spurt = true;
+                }
+
+                return Ok(SourceEvent::Frame(frame));
+            }
+
+            match self.silence_mode {
+                SilenceMode::Forward => return Ok(SourceEvent::Frame(frame)),
+                SilenceMode::Drop => {
+                    self.silence_replaced = true;
+                    continue;
+                }
+                SilenceMode::ComfortNoise => {
+                    self.silence_replaced = true;
+                    return Ok(SourceEvent::Frame(self.make_comfort_noise(&frame)));
+                }
+            }
+        }
+    }
+}
+
+fn rms<S: Sample>(samples: &[S]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+
+    let sum: f32 = samples
+        .iter()
+        .map(|sample| sample.to_sample::<f32>().powi(2))
+        .sum();
+
+    (sum / samples.len() as f32).sqrt()
+}
+
+/// Generate uniform white noise with the given RMS level
+fn comfort_noise<S>(len: usize, level: f32, state: &mut u32) -> Samples
+where
+    S: Sample,
+    Samples: From<Vec<S>>,
+{
+    // A uniform distribution in -a..a has an RMS of a / sqrt(3)
+    let amplitude = level * 3f32.sqrt();
+
+    let samples: Vec<S> = (0..len)
+        .map(|_| {
+            // xorshift32
+            *state ^= *state << 13;
+            *state ^= *state >> 17;
+            *state ^= *state << 5;
+
+            let noise = (*state as f32 / u32::MAX as f32) * 2.0 - 1.0;
+
+            (noise * amplitude).to_sample::<S>()
+        })
+        .collect();
+
+    Samples::from(samples)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ezk::ConfigRange;
+    use ezk_audio::{Channels, Format, SampleRate};
+    use std::collections::VecDeque;
+
+    const LOUD: i16 = i16::MAX / 2;
+    const QUIET: i16 = 10;
+
+    struct TestSource {
+        frames: VecDeque<Frame<RawAudio>>,
+    }
+
+    impl TestSource {
+        /// Create a source of 20ms frames with the given amplitudes
+        fn new(amplitudes: &[i16]) -> Self {
+            let frames = amplitudes
+                .iter()
+                .enumerate()
+                .map(|(i, &amplitude)| {
+                    // Alternate the sign to get a signal with an RMS level of the amplitude
+                    let samples: Vec<i16> = (0..160)
+                        .map(|j| if j % 2 == 0 { amplitude } else { -amplitude })
+                        .collect();
+
+                    Frame::new(
+                        RawAudioFrame {
+                            sample_rate: SampleRate(8000),
+                            channels: Channels::NotPositioned(1),
+                            samples: Samples::from(samples),
+                        },
+                        i as u64 * 160,
+                    )
+                })
+                .collect();
+
+            Self { frames }
+        }
+    }
+
+    impl Source for TestSource {
+        type MediaType = RawAudio;
+
+        async fn capabilities(&mut self) -> Result<Vec<RawAudioConfigRange>> {
+            Ok(vec![RawAudioConfigRange::any()])
+        }
+
+        async fn negotiate_config(
+            &mut self,
+            _available: Vec<RawAudioConfigRange>,
+        ) -> Result<RawAudioConfig> {
+            Ok(RawAudioConfig {
+                sample_rate: SampleRate(8000),
+                channels: Channels::NotPositioned(1),
+                format: Format::I16,
+            })
+        }
+
+        async fn next_event(&mut self) -> Result<SourceEvent<RawAudio>> {
+            match self.frames.pop_front() {
+                Some(frame) => Ok(SourceEvent::Frame(frame)),
+                None => Ok(SourceEvent::EndOfData),
+            }
+        }
+    }
+
+    async fn collect_frames(vad: &mut VoiceActivityDetector<TestSource>) -> Vec<Frame<RawAudio>> {
+        let mut frames = vec![];
+
+        while let SourceEvent::Frame(frame) = vad.next_event().await.unwrap() {
+            frames.push(frame);
+        }
+
+        frames
+    }
+
+    fn timestamps_and_talk_spurts(frames: &[Frame<RawAudio>]) -> Vec<(u64, bool)> {
+        frames
+            .iter()
+            .map(|frame| (frame.timestamp, frame.talk_spurt))
+            .collect()
+    }
+
+    #[test]
+    fn hangover() {
+        let mut vad = VoiceActivityDetector::new(TestSource::new(&[])).with_hangover(2);
+
+        vad.classify(0.5);
+        assert!(vad.is_active());
+
+        vad.classify(0.0001);
+        assert!(vad.is_active());
+        vad.classify(0.0001);
+        assert!(vad.is_active());
+
+        vad.classify(0.0001);
+        assert!(!vad.is_active());
+
+        // Speech restarts the hangover
+        vad.classify(0.5);
+        vad.classify(0.0001);
+        assert!(vad.is_active());
+    }
+
+    #[tokio::test]
+    async fn forward_silence() {
+        let mut vad = VoiceActivityDetector::new(TestSource::new(&[LOUD, QUIET, QUIET, LOUD]))
+            .with_hangover(0);
+
+        let frames = collect_frames(&mut vad).await;
+
+        assert_eq!(
+            timestamps_and_talk_spurts(&frames),
+            [(0, false), (160, false), (320, false), (480, false)]
+        );
+    }
+
+    #[tokio::test]
+    async fn drop_silence() {
+        let mut vad =
+            VoiceActivityDetector::new(TestSource::new(&[LOUD, QUIET, QUIET, LOUD, LOUD]))
+                .with_hangover(0)
+                .with_silence_mode(SilenceMode::Drop);
+
+        let frames = collect_frames(&mut vad).await;
+
+        assert_eq!(
+            timestamps_and_talk_spurts(&frames),
+            [(0, false), (480, true), (640, false)]
+        );
+    }
+
+    #[tokio::test]
+    async fn comfort_noise() {
+        let mut vad = VoiceActivityDetector::new(TestSource::new(&[LOUD, QUIET, QUIET, LOUD]))
+            .with_hangover(0)
+            .with_silence_mode(SilenceMode::ComfortNoise);
+
+        let frames = collect_frames(&mut vad).await;
+
+        assert_eq!(
+            timestamps_and_talk_spurts(&frames),
+            [(0, false), (160, false), (320, false), (480, true)]
+        );
+
+        // The comfort noise replaces the silence, but stays below its level
+        for frame in &frames[1..3] {
+            let Samples::I16(samples) = &frame.data().samples else {
+                panic!("unexpected sample format")
+            };
+
+            assert!(samples.iter().any(|&sample| sample != QUIET));
+            assert!(rms(samples) <= rms(&[QUIET]));
+        }
+    }
+}
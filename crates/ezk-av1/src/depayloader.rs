@@ -0,0 +1,98 @@
+use crate::{
+    obu::{read_leb128, Obu, OBU_TEMPORAL_DELIMITER, TEMPORAL_DELIMITER},
+    AGGREGATION_HEADER_Y, AGGREGATION_HEADER_Z, AV1,
+};
+use bytes::Bytes;
+use ezk_rtp::{DePayloader, FrameAssemblyResult};
+
+pub struct AV1DePayloader;
+
+impl DePayloader<AV1> for AV1DePayloader {
+    const FRAGMENTED: bool = true;
+
+    fn depayload(&mut self, payload: &[u8]) -> Bytes {
+        depayload_temporal_unit(&[payload]).unwrap_or_default()
+    }
+
+    fn depayload_fragments(
+        &mut self,
+        fragments: &[&[u8]],
+        result: FrameAssemblyResult,
+    ) -> Option<Bytes> {
+        // A temporal unit with missing OBUs cannot be decoded
+        if result != FrameAssemblyResult::Complete {
+            return None;
+        }
+
+        depayload_temporal_unit(fragments)
+    }
+}
+
+/// Reassemble all RTP payloads of a temporal unit into the low overhead bitstream format, starting with the
+/// temporal delimiter removed by the sender.
+///
+/// Returns `None` if a payload is malformed or the temporal unit contains no complete OBUs.
+pub(crate) fn depayload_temporal_unit(payloads: &[&[u8]]) -> Option<Bytes> {
+    let mut temporal_unit = TEMPORAL_DELIMITER.to_vec();
+    let mut obu = vec![];
+
+    for payload in payloads {
+        let (&aggregation_header, mut rest) = payload.split_first()?;
+
+        let continues_previous = aggregation_header & AGGREGATION_HEADER_Z != 0;
+        let continues_next = aggregation_header & AGGREGATION_HEADER_Y != 0;
+        let element_count = (aggregation_header >> 4) & 0b11;
+
+        let mut index = 0;
+
+        while !rest.is_empty() {
+            index += 1;
+
+            // With W != 0 the last element has no length field
+            let element = if index == element_count {
+                std::mem::take(&mut rest)
+            } else {
+                let (len, rest_) = read_leb128(rest)?;
+                let len = usize::try_from(len).ok()?;
+
+                if len > rest_.len() {
+                    return None;
+                }
+
+                let (element, rest_) = rest_.split_at(len);
+                rest = rest_;
+                element
+            };
+
+            if index == 1 && continues_previous {
+                if obu.is_empty() {
+                    // Fragment of an OBU whose start isn't part of this temporal unit
+                    continue;
+                }
+            } else {
+                obu.clear();
+            }
+
+            obu.extend_from_slice(element);
+
+            if rest.is_empty() && continues_next {
+                continue;
+            }
+
+            if let Some((parsed, _)) = Obu::parse(&obu) {
+                // Senders should not transmit temporal delimiters, don't duplicate them if they do
+                if parsed.obu_type() != OBU_TEMPORAL_DELIMITER {
+                    parsed.write_with_size(&mut temporal_unit);
+                }
+            }
+
+            obu.clear();
+        }
+    }
+
+    if temporal_unit.len() == TEMPORAL_DELIMITER.len() {
+        None
+    } else {
+        Some(Bytes::from(temporal_unit))
+    }
+}
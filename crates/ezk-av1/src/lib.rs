@@ -0,0 +1,83 @@
+//! AV1 RTP payload format
+//!
+//! Frames of the [`AV1`] media type contain a single temporal unit in the low overhead bitstream format, where every
+//! OBU has its size field set. Depayloaded temporal units start with a temporal delimiter OBU, which is not
+//! transmitted over RTP.
+
+use bytes::Bytes;
+use ezk::{ConfigRange, MediaType};
+use ezk_rtp::Payloadable;
+
+mod depayloader;
+mod obu;
+mod payloader;
+
+pub use depayloader::AV1DePayloader;
+pub use payloader::AV1Payloader;
+
+const AGGREGATION_HEADER_Z: u8 = 0b1000_0000;
+const AGGREGATION_HEADER_Y: u8 = 0b0100_0000;
+const AGGREGATION_HEADER_N: u8 = 0b0000_1000;
+
+#[derive(Debug)]
+pub enum AV1 {}
+
+impl MediaType for AV1 {
+    type ConfigRange = AV1ConfigRange;
+    type Config = AV1Config;
+    type FrameData = Bytes;
+}
+
+#[derive(Debug, Clone)]
+pub struct AV1ConfigRange;
+
+impl ConfigRange for AV1ConfigRange {
+    type Config = AV1Config;
+
+    fn any() -> Self {
+        Self {}
+    }
+
+    fn intersect(&self, _other: &Self) -> Option<Self> {
+        Some(Self {})
+    }
+
+    fn contains(&self, _config: &Self::Config) -> bool {
+        true
+    }
+}
+
+#[derive(Default, Debug, Clone)]
+pub struct AV1Config;
+
+impl Payloadable for AV1 {
+    type Payloader = AV1Payloader;
+    type DePayloader = AV1DePayloader;
+
+    const STATIC_PT: Option<u8> = None;
+    const RTP_CLOCK_RATE: u32 = 90000;
+
+    fn make_payloader(_: Self::Config) -> Self::Payloader {
+        AV1Payloader {}
+    }
+
+    fn make_depayloader(_: Vec<Self::ConfigRange>) -> (Self::Config, Self::DePayloader) {
+        (Self::Config {}, AV1DePayloader {})
+    }
+}
+
+/// Returns if the temporal unit can be decoded without any previous ones.
+///
+/// Encoders emit a sequence header with every keyframe, so its presence is used to detect them.
+pub fn is_keyframe(temporal_unit: &[u8]) -> bool {
+    obu::obus(temporal_unit).any(|obu| obu.obu_type() == obu::OBU_SEQUENCE_HEADER)
+}
+
+/// Returns if the RTP payload is the first packet of a new coded video sequence (the `N` bit is set).
+///
+/// After requesting a keyframe (e.g. using a PLI) the receiver can drop all packets until this returns `true`.
+pub fn starts_coded_video_sequence(payload: &[u8]) -> bool {
+    payload
+        .first()
+        .is_some_and(|aggregation_header| aggregation_header & AGGREGATION_HEADER_N != 0)
+}
@@ -0,0 +1,125 @@
+//! Minimal parsing of AV1 open bitstream units (OBUs), just enough to repacketize them
+
+pub(crate) const OBU_SEQUENCE_HEADER: u8 = 1;
+pub(crate) const OBU_TEMPORAL_DELIMITER: u8 = 2;
+pub(crate) const OBU_TILE_LIST: u8 = 8;
+pub(crate) const OBU_PADDING: u8 = 15;
+
+const OBU_EXTENSION_FLAG: u8 = 0b0000_0100;
+const OBU_HAS_SIZE_FIELD: u8 = 0b0000_0010;
+
+/// Temporal delimiter OBU with a size field, starts every temporal unit in the low overhead bitstream format
+pub(crate) const TEMPORAL_DELIMITER: [u8; 2] =
+    [(OBU_TEMPORAL_DELIMITER << 3) | OBU_HAS_SIZE_FIELD, 0];
+
+/// Single OBU, split into its header and payload
+pub(crate) struct Obu<'a> {
+    /// OBU header byte, `obu_has_size_field` is always cleared
+    pub(crate) header: u8,
+    pub(crate) extension: Option<u8>,
+    pub(crate) payload: &'a [u8],
+}
+
+impl<'a> Obu<'a> {
+    pub(crate) fn obu_type(&self) -> u8 {
+        (self.header >> 3) & 0b1111
+    }
+
+    /// Parse a single OBU from the start of `data`, returns the OBU and the remaining bytes.
+    ///
+    /// If the OBU has no size field it extends to the end of `data`.
+    pub(crate) fn parse(data: &'a [u8]) -> Option<(Self, &'a [u8])> {
+        let (&header, mut rest) = data.split_first()?;
+
+        let extension = if header & OBU_EXTENSION_FLAG != 0 {
+            let (&extension, rest_) = rest.split_first()?;
+            rest = rest_;
+            Some(extension)
+        } else {
+            None
+        };
+
+        let (payload, rest) = if header & OBU_HAS_SIZE_FIELD != 0 {
+            let (size, rest) = read_leb128(rest)?;
+            let size = usize::try_from(size).ok()?;
+
+            if size > rest.len() {
+                return None;
+            }
+
+            rest.split_at(size)
+        } else {
+            (rest, &[][..])
+        };
+
+        let obu = Self {
+            header: header & !OBU_HAS_SIZE_FIELD,
+            extension,
+            payload,
+        };
+
+        Some((obu, rest))
+    }
+
+    /// Length of the OBU without a size field
+    pub(crate) fn len_without_size(&self) -> usize {
+        1 + usize::from(self.extension.is_some()) + self.payload.len()
+    }
+
+    /// Write the OBU without a size field, as used inside RTP packets
+    pub(crate) fn write_without_size(&self, dst: &mut Vec<u8>) {
+        dst.push(self.header);
+        dst.extend(self.extension);
+        dst.extend_from_slice(self.payload);
+    }
+
+    /// Write the OBU with a size field, as used in the low overhead bitstream format
+    pub(crate) fn write_with_size(&self, dst: &mut Vec<u8>) {
+        dst.push(self.header | OBU_HAS_SIZE_FIELD);
+        dst.extend(self.extension);
+        write_leb128(dst, self.payload.len() as u64);
+        dst.extend_from_slice(self.payload);
+    }
+}
+
+/// Iterate over all OBUs of a temporal unit in the low overhead bitstream format
+pub(crate) fn obus(mut data: &[u8]) -> impl Iterator<Item = Obu<'_>> {
+    std::iter::from_fn(move || {
+        let (obu, rest) = Obu::parse(data)?;
+        data = rest;
+        Some(obu)
+    })
+}
+
+pub(crate) fn read_leb128(data: &[u8]) -> Option<(u64, &[u8])> {
+    let mut value = 0u64;
+
+    for (i, &byte) in data.iter().enumerate().take(8) {
+        value |= u64::from(byte & 0x7F) << (i * 7);
+
+        if byte & 0x80 == 0 {
+            return Some((value, &data[i + 1..]));
+        }
+    }
+
+    None
+}
+
+pub(crate) fn write_leb128(dst: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+
+        if value == 0 {
+            dst.push(byte);
+            return;
+        }
+
+        dst.push(byte | 0x80);
+    }
+}
+
+pub(crate) fn leb128_len(value: usize) -> usize {
+    let bits = usize::BITS - value.leading_zeros();
+    (bits as usize).div_ceil(7).max(1)
+}
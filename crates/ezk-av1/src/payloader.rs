@@ -0,0 +1,163 @@
+use crate::{
+    obu::{self, leb128_len, write_leb128},
+    AGGREGATION_HEADER_N, AGGREGATION_HEADER_Y, AGGREGATION_HEADER_Z, AV1,
+};
+use bytes::Bytes;
+use ezk::Frame;
+use ezk_rtp::Payloader;
+use std::mem::take;
+
+/// Smallest packet which can hold the aggregation header, a length field and a single byte of an OBU
+const MIN_PACKET_SIZE: usize = 3;
+
+pub struct AV1Payloader;
+
+impl Payloader<AV1> for AV1Payloader {
+    fn payload(&mut self, frame: Frame<AV1>, max_size: usize) -> impl Iterator<Item = Bytes> + '_ {
+        payload_temporal_unit(frame.data(), max_size).into_iter()
+    }
+}
+
+/// Split a temporal unit into RTP payloads of at most `max_size` bytes.
+///
+/// Every OBU element is prefixed with its length (W = 0), OBUs which don't fit into a packet are fragmented.
+pub(crate) fn payload_temporal_unit(temporal_unit: &[u8], max_size: usize) -> Vec<Bytes> {
+    let max_size = max_size.max(MIN_PACKET_SIZE);
+
+    let mut packets = vec![];
+    let mut packet = vec![0];
+    let mut element = vec![];
+    let mut new_coded_video_sequence = false;
+
+    for obu in obu::obus(temporal_unit) {
+        match obu.obu_type() {
+            // Must not be transmitted over RTP
+            obu::OBU_TEMPORAL_DELIMITER | obu::OBU_TILE_LIST | obu::OBU_PADDING => continue,
+            obu::OBU_SEQUENCE_HEADER => new_coded_video_sequence = true,
+            _ => {}
+        }
+
+        element.clear();
+        element.reserve(obu.len_without_size());
+        obu.write_without_size(&mut element);
+
+        let mut remaining = &element[..];
+
+        while !remaining.is_empty() {
+            let space = max_size - packet.len();
+
+            if space < MIN_PACKET_SIZE - 1 {
+                packets.push(take(&mut packet));
+                packet.push(0);
+                continue;
+            }
+
+            let mut len = remaining.len();
+
+            if leb128_len(len) + len > space {
+                len = space - leb128_len(space);
+            }
+
+            write_leb128(&mut packet, len as u64);
+            packet.extend_from_slice(&remaining[..len]);
+            remaining = &remaining[len..];
+
+            if !remaining.is_empty() {
+                // OBU continues in the next packet
+                packet[0] |= AGGREGATION_HEADER_Y;
+                packets.push(take(&mut packet));
+                packet.push(AGGREGATION_HEADER_Z);
+            }
+        }
+    }
+
+    if packet.len() > 1 {
+        packets.push(packet);
+    }
+
+    if new_coded_video_sequence {
+        if let Some(first) = packets.first_mut() {
+            first[0] |= AGGREGATION_HEADER_N;
+        }
+    }
+
+    packets.into_iter().map(Bytes::from).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{depayloader::depayload_temporal_unit, is_keyframe, starts_coded_video_sequence};
+
+    fn make_obu(obu_type: u8, payload_len: usize) -> Vec<u8> {
+        let mut obu = vec![(obu_type << 3) | 0b10];
+        write_leb128(&mut obu, payload_len as u64);
+        obu.extend((0..payload_len).map(|i| i as u8));
+        obu
+    }
+
+    /// Payload and depayload the temporal unit, returns the depayloaded temporal unit without its temporal delimiter
+    fn roundtrip(temporal_unit: &[u8], max_size: usize) -> Vec<u8> {
+        let packets = payload_temporal_unit(temporal_unit, max_size);
+        assert!(packets.iter().all(|packet| packet.len() <= max_size));
+
+        let packets: Vec<&[u8]> = packets.iter().map(|p| &p[..]).collect();
+        let depayloaded = depayload_temporal_unit(&packets).unwrap();
+
+        let temporal_delimiter = make_obu(2, 0);
+        assert!(depayloaded.starts_with(&temporal_delimiter));
+
+        depayloaded[temporal_delimiter.len()..].to_vec()
+    }
+
+    #[test]
+    fn aggregate_small_obus() {
+        let temporal_unit = [make_obu(1, 10), make_obu(6, 20)].concat();
+
+        let packets = payload_temporal_unit(&temporal_unit, 1200);
+        assert_eq!(packets.len(), 1);
+        assert!(starts_coded_video_sequence(&packets[0]));
+
+        assert_eq!(roundtrip(&temporal_unit, 1200), temporal_unit);
+        assert!(is_keyframe(&temporal_unit));
+    }
+
+    #[test]
+    fn fragment_large_obus() {
+        let temporal_unit = [make_obu(6, 5000), make_obu(6, 300)].concat();
+
+        let packets = payload_temporal_unit(&temporal_unit, 1200);
+        assert!(packets.len() > 4);
+        assert!(!starts_coded_video_sequence(&packets[0]));
+
+        for max_size in [3, 4, 100, 127, 128, 129, 1200] {
+            assert_eq!(roundtrip(&temporal_unit, max_size), temporal_unit);
+        }
+
+        assert!(!is_keyframe(&temporal_unit));
+    }
+
+    #[test]
+    fn drop_temporal_delimiter() {
+        let frame = make_obu(6, 10);
+        let temporal_unit = [make_obu(2, 0), frame.clone()].concat();
+
+        // The depayloader restores exactly one temporal delimiter
+        assert_eq!(roundtrip(&temporal_unit, 1200), frame);
+    }
+
+    #[test]
+    fn keep_single_temporal_delimiter() {
+        let frame = make_obu(6, 10);
+
+        // Sender which doesn't remove the temporal delimiter, aggregation header with W = 2
+        let mut payload = vec![0b0010_0000];
+        write_leb128(&mut payload, 1);
+        payload.push(2 << 3);
+        payload.push(6 << 3);
+        payload.extend((0..10).map(|i| i as u8));
+
+        let depayloaded = depayload_temporal_unit(&[&payload]).unwrap();
+        assert_eq!(&depayloaded[..], [make_obu(2, 0), frame].concat());
+    }
+}
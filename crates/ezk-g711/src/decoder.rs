@@ -100,14 +100,17 @@ where
 
                 let samples = Samples::from(S::MediaType::decode(data));
 
-                Ok(SourceEvent::Frame(Frame::new(
-                    RawAudioFrame {
-                        sample_rate: config.sample_rate,
-                        channels: config.channels.clone(),
-                        samples,
-                    },
-                    frame.timestamp,
-                )))
+                Ok(SourceEvent::Frame(
+                    Frame::new(
+                        RawAudioFrame {
+                            sample_rate: config.sample_rate,
+                            channels: config.channels.clone(),
+                            samples,
+                        },
+                        frame.timestamp,
+                    )
+                    .with_capture_time(frame.capture_time),
+                ))
             }
             SourceEvent::EndOfData => {
                 self.config = None;
@@ -78,10 +78,11 @@ where
                     unreachable!()
                 };
 
-                Ok(SourceEvent::Frame(Frame::new(
-                    M::encode(samples).into(),
-                    frame.timestamp,
-                )))
+                Ok(SourceEvent::Frame(
+                    Frame::new(M::encode(samples).into(), frame.timestamp)
+                        .with_capture_time(frame.capture_time)
+                        .with_talk_spurt(frame.talk_spurt),
+                ))
             }
             SourceEvent::EndOfData => Ok(SourceEvent::EndOfData),
             SourceEvent::RenegotiationNeeded => Ok(SourceEvent::RenegotiationNeeded),
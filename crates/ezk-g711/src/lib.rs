@@ -57,6 +57,7 @@ macro_rules! pcmx {
             type DePayloader = G711DePayloader;
 
             const STATIC_PT: Option<u8> = Some($pt);
+            const RTP_CLOCK_RATE: u32 = 8000;
 
             fn make_payloader(_: Self::Config) -> Self::Payloader {
                 G711Payloader {}
@@ -102,14 +102,17 @@ where
 
                 let samples = Samples::from(stream.decoder.decode(data));
 
-                Ok(SourceEvent::Frame(Frame::new(
-                    RawAudioFrame {
-                        sample_rate: stream.config.sample_rate,
-                        channels: stream.config.channels.clone(),
-                        samples,
-                    },
-                    frame.timestamp * 2,
-                )))
+                Ok(SourceEvent::Frame(
+                    Frame::new(
+                        RawAudioFrame {
+                            sample_rate: stream.config.sample_rate,
+                            channels: stream.config.channels.clone(),
+                            samples,
+                        },
+                        frame.timestamp * 2,
+                    )
+                    .with_capture_time(frame.capture_time),
+                ))
             }
             SourceEvent::EndOfData => {
                 self.stream = None;
@@ -89,10 +89,11 @@ where
                     unreachable!()
                 };
 
-                Ok(SourceEvent::Frame(Frame::new(
-                    stream.encoder.encode(samples).into(),
-                    frame.timestamp / 2,
-                )))
+                Ok(SourceEvent::Frame(
+                    Frame::new(stream.encoder.encode(samples).into(), frame.timestamp / 2)
+                        .with_capture_time(frame.capture_time)
+                        .with_talk_spurt(frame.talk_spurt),
+                ))
             }
             SourceEvent::EndOfData => Ok(SourceEvent::EndOfData),
             SourceEvent::RenegotiationNeeded => Ok(SourceEvent::RenegotiationNeeded),
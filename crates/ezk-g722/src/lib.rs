@@ -48,6 +48,10 @@ impl Payloadable for G722 {
 
     const STATIC_PT: Option<u8> = Some(9);
 
+    // RFC 3551 mandates an 8000 Hz clock rate despite the 16 kHz sampling rate for historic reasons.
+    // The encoder & decoder convert the frame timestamps accordingly.
+    const RTP_CLOCK_RATE: u32 = 8000;
+
     fn make_payloader(_: Self::Config) -> Self::Payloader {
         G722Payloader {}
     }
@@ -1,8 +1,19 @@
-use crate::{DePayloader, Payloadable, Rtp};
+use crate::{
+    session::guess_sequence_number, DePayloader, FrameAssemblyResult, Payloadable, Rtp, RtpPacket,
+};
+use bytes::Bytes;
 use ezk::{ConfigRange, Frame, NextEventIsCancelSafe, Result, Source, SourceEvent};
+use std::{collections::BTreeMap, time::Instant};
+
+const DEFAULT_MAX_ASSEMBLY_WINDOW: usize = 64;
+/// Maximum forward jump of the sequence number before it is considered a restart of the stream (RFC 3550 A.1)
+const MAX_DROPOUT: u64 = 3000;
+/// Maximum backward jump of the sequence number before it is considered a restart of the stream (RFC 3550 A.1)
+const MAX_MISORDER: u64 = 100;
 
 pub struct DePacketizer<S: Source<MediaType = Rtp>, M: Payloadable> {
     source: S,
+    max_assembly_window: usize,
 
     stream: Option<Stream<M>>,
 }
@@ -14,6 +25,10 @@ impl<S: Source<MediaType = Rtp> + NextEventIsCancelSafe, M: Payloadable> NextEve
 
 struct Stream<M: Payloadable> {
     depayloader: M::DePayloader,
+    assembler: FrameAssembler,
+
+    /// The source ended, flush the assembler before passing on the end of data
+    end_of_data: bool,
 }
 
 impl<S, M> DePacketizer<S, M>
@@ -24,9 +39,18 @@ where
     pub fn new(source: S) -> Self {
         Self {
             source,
+            max_assembly_window: DEFAULT_MAX_ASSEMBLY_WINDOW,
             stream: None,
         }
     }
+
+    /// Maximum number of packets to wait for missing fragments of a frame before giving up on them.
+    ///
+    /// Only relevant for media types whose frames may be fragmented, see [`DePayloader::FRAGMENTED`].
+    pub fn with_max_assembly_window(mut self, max_assembly_window: usize) -> Self {
+        self.max_assembly_window = max_assembly_window;
+        self
+    }
 }
 
 impl<S, M> Source for DePacketizer<S, M>
@@ -45,7 +69,11 @@ where
     async fn negotiate_config(&mut self, available: Vec<M::ConfigRange>) -> Result<M::Config> {
         let (config, depayloader) = M::make_depayloader(available);
 
-        self.stream = Some(Stream { depayloader });
+        self.stream = Some(Stream {
+            depayloader,
+            assembler: FrameAssembler::new(self.max_assembly_window),
+            end_of_data: false,
+        });
 
         Ok(config)
     }
@@ -55,17 +83,440 @@ where
             return Ok(SourceEvent::RenegotiationNeeded);
         };
 
-        let frame = match self.source.next_event().await? {
-            SourceEvent::Frame(frame) => frame,
-            SourceEvent::EndOfData => return Ok(SourceEvent::EndOfData),
-            SourceEvent::RenegotiationNeeded => return Ok(SourceEvent::RenegotiationNeeded),
+        loop {
+            while let Some(frame) = if stream.end_of_data {
+                stream.assembler.flush()
+            } else {
+                stream.assembler.pop()
+            } {
+                let fragments: Vec<&[u8]> = frame.payloads.iter().map(|p| &p[..]).collect();
+
+                if let Some(data) = stream
+                    .depayloader
+                    .depayload_fragments(&fragments, frame.result)
+                {
+                    return Ok(SourceEvent::Frame(
+                        Frame::new(data, frame.timestamp).with_capture_time(frame.capture_time),
+                    ));
+                }
+            }
+
+            if stream.end_of_data {
+                stream.end_of_data = false;
+                return Ok(SourceEvent::EndOfData);
+            }
+
+            let frame = match self.source.next_event().await? {
+                SourceEvent::Frame(frame) => frame,
+                SourceEvent::EndOfData => {
+                    // Pass on the frames still waiting for missing fragments first
+                    stream.end_of_data = true;
+                    continue;
+                }
+                SourceEvent::RenegotiationNeeded => return Ok(SourceEvent::RenegotiationNeeded),
+            };
+
+            let frame_timestamp = frame.timestamp;
+            let capture_time = frame.capture_time;
+
+            if !<M::DePayloader as DePayloader<M>>::FRAGMENTED {
+                let rtp_packet = frame.into_data();
+
+                let data = stream.depayloader.depayload(rtp_packet.get().payload());
+
+                return Ok(SourceEvent::Frame(
+                    Frame::new(data, frame_timestamp).with_capture_time(capture_time),
+                ));
+            }
+
+            stream
+                .assembler
+                .push(frame_timestamp, capture_time, frame.data());
+        }
+    }
+}
+
+/// Reorders RTP packets using their extended sequence number and groups them into frames
+struct FrameAssembler {
+    max_window: usize,
+
+    /// Extended sequence number of the next expected packet
+    next_sequence_number: Option<u64>,
+
+    /// extended sequence number -> fragment
+    fragments: BTreeMap<u64, Fragment>,
+
+    /// Packet with an unexpected sequence number, if the next packet follows it the stream has been restarted
+    probable_restart: Option<(u16, Fragment)>,
+}
+
+struct Fragment {
+    rtp_timestamp: u32,
+    frame_timestamp: u64,
+    capture_time: Option<Instant>,
+    marker: bool,
+    payload: Bytes,
+}
+
+struct AssembledFrame {
+    timestamp: u64,
+    capture_time: Option<Instant>,
+    payloads: Vec<Bytes>,
+    result: FrameAssemblyResult,
+}
+
+impl FrameAssembler {
+    fn new(max_window: usize) -> Self {
+        Self {
+            max_window,
+            next_sequence_number: None,
+            fragments: BTreeMap::new(),
+            probable_restart: None,
+        }
+    }
+
+    fn push(&mut self, frame_timestamp: u64, capture_time: Option<Instant>, packet: &RtpPacket) {
+        let packet = packet.get();
+        let received_sequence_number = packet.sequence_number();
+
+        let fragment = Fragment {
+            rtp_timestamp: packet.timestamp(),
+            frame_timestamp,
+            capture_time,
+            marker: packet.marker_bit(),
+            payload: Bytes::copy_from_slice(packet.payload()),
+        };
+
+        let Some(next) = self.next_sequence_number else {
+            let sequence_number = u64::from(received_sequence_number);
+            self.next_sequence_number = Some(sequence_number);
+            self.fragments.insert(sequence_number, fragment);
+            return;
+        };
+
+        let sequence_number = guess_sequence_number(next, received_sequence_number);
+
+        let highest = self
+            .fragments
+            .last_key_value()
+            .map_or(next, |(&highest, _)| highest.max(next));
+
+        if sequence_number > highest + MAX_DROPOUT || sequence_number + MAX_MISORDER < next {
+            match self.probable_restart.take() {
+                Some((previous, previous_fragment))
+                    if previous.wrapping_add(1) == received_sequence_number =>
+                {
+                    // Two sequential packets far outside the expected range, the sender restarted its stream
+                    let previous = u64::from(previous);
+
+                    self.fragments.clear();
+                    self.fragments.insert(previous, previous_fragment);
+                    self.fragments.insert(previous + 1, fragment);
+                    self.next_sequence_number = Some(previous);
+                }
+                _ => self.probable_restart = Some((received_sequence_number, fragment)),
+            }
+
+            return;
+        }
+
+        self.probable_restart = None;
+
+        if sequence_number < next {
+            // Packet arrived too late, the frame it belongs to has already been assembled
+            return;
+        }
+
+        self.fragments.entry(sequence_number).or_insert(fragment);
+    }
+
+    fn pop(&mut self) -> Option<AssembledFrame> {
+        self.pop_frame(false)
+    }
+
+    /// Pop the buffered frames without waiting for missing fragments, used at the end of the stream
+    fn flush(&mut self) -> Option<AssembledFrame> {
+        self.pop_frame(true)
+    }
+
+    fn pop_frame(&mut self, force: bool) -> Option<AssembledFrame> {
+        let next = self.next_sequence_number?;
+        let (first_sequence_number, first_rtp_timestamp) = self
+            .fragments
+            .first_key_value()
+            .map(|(&sequence_number, fragment)| (sequence_number, fragment.rtp_timestamp))?;
+
+        // Try to find the end of a frame without any gaps
+        if first_sequence_number == next {
+            if let Some(end) = self.find_frame_end(first_rtp_timestamp, true) {
+                return Some(self.take(next, end));
+            }
+        }
+
+        // Fragments are missing, wait for them until the window is exceeded
+        let (&last_sequence_number, _) = self.fragments.last_key_value()?;
+
+        if !force && last_sequence_number - next < self.max_window as u64 {
+            return None;
+        }
+
+        if let Some(end) = self.find_frame_end(first_rtp_timestamp, false) {
+            return Some(self.take(next, end));
+        }
+
+        // The end of the frame hasn't been received
+        let mut frame = self.take(next, last_sequence_number);
+
+        frame.result = match frame.result {
+            FrameAssemblyResult::Complete => FrameAssemblyResult::Incomplete(1),
+            FrameAssemblyResult::Incomplete(lost) => FrameAssemblyResult::Incomplete(lost + 1),
+        };
+
+        Some(frame)
+    }
+
+    /// Find the sequence number of the last fragment of the first frame in the buffer.
+    ///
+    /// The end of the frame is marked by the marker bit or a fragment with a different timestamp. In the latter case
+    /// missing fragments in between are attributed to the first frame, as its marker packet may have been lost.
+    fn find_frame_end(&self, rtp_timestamp: u32, contiguous: bool) -> Option<u64> {
+        let mut previous: Option<u64> = None;
+
+        for (&sequence_number, fragment) in &self.fragments {
+            if contiguous && previous.is_some_and(|previous| previous + 1 != sequence_number) {
+                return None;
+            }
+
+            if fragment.rtp_timestamp != rtp_timestamp {
+                // A new frame started, the previous one didn't set the marker bit or it was lost
+                return previous.map(|_| sequence_number - 1);
+            }
+
+            if fragment.marker {
+                return Some(sequence_number);
+            }
+
+            previous = Some(sequence_number);
+        }
+
+        None
+    }
+
+    /// Remove all fragments in the given sequence number range and assemble them into a frame
+    fn take(&mut self, start: u64, end: u64) -> AssembledFrame {
+        let remaining = self.fragments.split_off(&(end + 1));
+        let fragments = std::mem::replace(&mut self.fragments, remaining);
+
+        self.next_sequence_number = Some(end + 1);
+
+        let lost = (end + 1 - start) - fragments.len() as u64;
+
+        let result = if lost == 0 {
+            FrameAssemblyResult::Complete
+        } else {
+            FrameAssemblyResult::Incomplete(lost)
         };
 
-        let frame_timestamp = frame.timestamp;
-        let rtp_packet = frame.into_data();
+        let (timestamp, capture_time) = fragments
+            .first_key_value()
+            .map(|(_, fragment)| (fragment.frame_timestamp, fragment.capture_time))
+            .unwrap_or_default();
+
+        AssembledFrame {
+            timestamp,
+            capture_time,
+            payloads: fragments.into_values().map(|f| f.payload).collect(),
+            result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rtp_types::RtpPacketBuilder;
+
+    fn make_packet(
+        sequence_number: u16,
+        timestamp: u32,
+        marker: bool,
+        payload: &[u8],
+    ) -> RtpPacket {
+        RtpPacket::new(
+            &RtpPacketBuilder::new()
+                .sequence_number(sequence_number)
+                .timestamp(timestamp)
+                .marker_bit(marker)
+                .payload(payload),
+        )
+    }
+
+    #[test]
+    fn reordered_fragments() {
+        let mut assembler = FrameAssembler::new(16);
+
+        assembler.push(0, None, &make_packet(10, 100, false, b"a"));
+        assembler.push(0, None, &make_packet(12, 100, true, b"c"));
+        assert!(assembler.pop().is_none());
+
+        assembler.push(0, None, &make_packet(11, 100, false, b"b"));
+
+        let frame = assembler.pop().unwrap();
+        assert_eq!(frame.result, FrameAssemblyResult::Complete);
+        assert_eq!(frame.payloads.concat(), b"abc");
+        assert!(assembler.pop().is_none());
+    }
+
+    #[test]
+    fn missing_marker_bit() {
+        let mut assembler = FrameAssembler::new(16);
+
+        assembler.push(0, None, &make_packet(1, 100, false, b"a"));
+        assembler.push(0, None, &make_packet(2, 100, false, b"b"));
+        assert!(assembler.pop().is_none());
+
+        assembler.push(0, None, &make_packet(3, 200, false, b"c"));
+
+        let frame = assembler.pop().unwrap();
+        assert_eq!(frame.result, FrameAssemblyResult::Complete);
+        assert_eq!(frame.payloads.concat(), b"ab");
+    }
+
+    #[test]
+    fn lost_fragment() {
+        let mut assembler = FrameAssembler::new(4);
+
+        assembler.push(0, None, &make_packet(1, 100, false, b"a"));
+        assembler.push(0, None, &make_packet(3, 100, true, b"c"));
+        assembler.push(0, None, &make_packet(4, 200, true, b"d"));
+        assert!(assembler.pop().is_none());
+
+        assembler.push(0, None, &make_packet(5, 300, true, b"e"));
+
+        let frame = assembler.pop().unwrap();
+        assert_eq!(frame.result, FrameAssemblyResult::Incomplete(1));
+        assert_eq!(frame.payloads.concat(), b"ac");
+
+        let frame = assembler.pop().unwrap();
+        assert_eq!(frame.result, FrameAssemblyResult::Complete);
+        assert_eq!(frame.payloads.concat(), b"d");
+
+        // late packet is ignored
+        assembler.push(0, None, &make_packet(2, 100, false, b"b"));
+
+        let frame = assembler.pop().unwrap();
+        assert_eq!(frame.result, FrameAssemblyResult::Complete);
+        assert_eq!(frame.payloads.concat(), b"e");
+        assert!(assembler.pop().is_none());
+    }
+
+    #[test]
+    fn sequence_restart() {
+        let mut assembler = FrameAssembler::new(16);
+
+        assembler.push(0, None, &make_packet(1000, 100, true, b"a"));
+        assert_eq!(assembler.pop().unwrap().payloads.concat(), b"a");
+
+        // Incomplete frame, which is never finished by the sender
+        assembler.push(0, None, &make_packet(1001, 200, false, b"b"));
+
+        // Sender restarts with a lower sequence number
+        assembler.push(0, None, &make_packet(5, 9000, true, b"x"));
+        assert!(assembler.pop().is_none());
+
+        assembler.push(0, None, &make_packet(6, 9100, true, b"y"));
+
+        let frame = assembler.pop().unwrap();
+        assert_eq!(frame.result, FrameAssemblyResult::Complete);
+        assert_eq!(frame.payloads.concat(), b"x");
+
+        let frame = assembler.pop().unwrap();
+        assert_eq!(frame.result, FrameAssemblyResult::Complete);
+        assert_eq!(frame.payloads.concat(), b"y");
+
+        assembler.push(0, None, &make_packet(7, 9200, true, b"z"));
+        assert_eq!(assembler.pop().unwrap().payloads.concat(), b"z");
+    }
+
+    #[test]
+    fn stray_packet_far_ahead() {
+        let mut assembler = FrameAssembler::new(16);
+
+        assembler.push(0, None, &make_packet(1, 100, true, b"a"));
+        assert_eq!(assembler.pop().unwrap().payloads.concat(), b"a");
+
+        assembler.push(0, None, &make_packet(30000, 5, true, b"x"));
+        assert!(assembler.pop().is_none());
+
+        assembler.push(0, None, &make_packet(2, 200, true, b"b"));
+        assembler.push(0, None, &make_packet(3, 300, true, b"c"));
+
+        let frame = assembler.pop().unwrap();
+        assert_eq!(frame.result, FrameAssemblyResult::Complete);
+        assert_eq!(frame.payloads.concat(), b"b");
+
+        let frame = assembler.pop().unwrap();
+        assert_eq!(frame.result, FrameAssemblyResult::Complete);
+        assert_eq!(frame.payloads.concat(), b"c");
+        assert!(assembler.pop().is_none());
+    }
+
+    #[test]
+    fn lost_marker_fragment() {
+        let mut assembler = FrameAssembler::new(4);
+
+        assembler.push(0, None, &make_packet(1, 100, false, b"a"));
+        assembler.push(0, None, &make_packet(2, 100, false, b"b"));
+        // 3 with the marker bit of the first frame is lost
+        assembler.push(0, None, &make_packet(4, 200, false, b"c"));
+        assembler.push(0, None, &make_packet(5, 200, true, b"d"));
+
+        // The window is exceeded
+        let frame = assembler.pop().unwrap();
+        assert_eq!(frame.result, FrameAssemblyResult::Incomplete(1));
+        assert_eq!(frame.payloads.concat(), b"ab");
+
+        let frame = assembler.pop().unwrap();
+        assert_eq!(frame.result, FrameAssemblyResult::Complete);
+        assert_eq!(frame.payloads.concat(), b"cd");
+    }
+
+    #[test]
+    fn small_window_gap_is_no_restart() {
+        let mut assembler = FrameAssembler::new(1);
+
+        assembler.push(0, None, &make_packet(1, 100, true, b"a"));
+        assert_eq!(assembler.pop().unwrap().payloads.concat(), b"a");
+
+        // 2 and 3 are lost
+        assembler.push(0, None, &make_packet(4, 400, true, b"d"));
+
+        let frame = assembler.pop().unwrap();
+        assert_eq!(frame.result, FrameAssemblyResult::Incomplete(2));
+        assert_eq!(frame.payloads.concat(), b"d");
+    }
+
+    #[test]
+    fn flush() {
+        let mut assembler = FrameAssembler::new(16);
+
+        assembler.push(0, None, &make_packet(1, 100, true, b"a"));
+        // 2 is lost
+        assembler.push(0, None, &make_packet(3, 200, true, b"c"));
+        assembler.push(0, None, &make_packet(4, 300, false, b"d"));
+
+        assert_eq!(assembler.pop().unwrap().payloads.concat(), b"a");
+        assert!(assembler.pop().is_none());
+
+        let frame = assembler.flush().unwrap();
+        assert_eq!(frame.result, FrameAssemblyResult::Incomplete(1));
+        assert_eq!(frame.payloads.concat(), b"c");
 
-        let data = stream.depayloader.depayload(rtp_packet.get().payload());
+        // The end of the last frame is missing
+        let frame = assembler.flush().unwrap();
+        assert_eq!(frame.result, FrameAssemblyResult::Incomplete(1));
+        assert_eq!(frame.payloads.concat(), b"d");
 
-        Ok(SourceEvent::Frame(Frame::new(data, frame_timestamp)))
+        assert!(assembler.flush().is_none());
     }
 }
@@ -11,9 +11,12 @@ mod session;
 pub use depacketizer::DePacketizer;
 pub use media_type::{Rtp, RtpConfig, RtpConfigRange};
 pub use ntp_timestamp::NtpTimestamp;
-pub use packetizer::Packetizer;
+pub use packetizer::{MarkerBitPolicy, Packetizer, StreamPosition, TimestampMode};
 pub use rtp_packet::*;
-pub use session::RtpSession;
+pub use session::{
+    RemoteReport, RemoteReportReceiver, RtpSession, StatsSample, SyncInfo, SDES_ITEM_CNAME,
+    SDES_ITEM_MID,
+};
 
 pub use rtcp_types;
 pub use rtp_types;
@@ -28,6 +31,12 @@ pub trait Payloadable: Sized + MediaType {
     /// Statically assigned payload type
     const STATIC_PT: Option<u8>;
 
+    /// Clock rate of the RTP timestamps, as advertised in the SDP rtpmap attribute.
+    ///
+    /// This may differ from the sample rate of the media (e.g. G.722 uses 8000 Hz for 16 kHz audio), in which case
+    /// the frame timestamps of this media type must already use the RTP clock rate.
+    const RTP_CLOCK_RATE: u32;
+
     /// Create the payload with the given configuration
     fn make_payloader(config: Self::Config) -> Self::Payloader;
 
@@ -39,8 +48,48 @@ pub trait Payloadable: Sized + MediaType {
 pub trait Payloader<M: MediaType>: Send + 'static {
     /// Payload a given frame
     fn payload(&mut self, frame: Frame<M>, max_size: usize) -> impl Iterator<Item = Bytes> + '_;
+
+    /// Called by the [`Packetizer`] with new reception reports of the remote receiver, see
+    /// [`Packetizer::with_remote_reports`].
+    ///
+    /// Allows adapting the packetization (e.g. adding redundancy) to the network conditions. Does nothing by default.
+    fn handle_remote_report(&mut self, report: &RemoteReport) {
+        let _ = report;
+    }
+}
+
+/// Result of assembling a frame from one or more RTP packets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameAssemblyResult {
+    /// All packets of the frame have been received
+    Complete,
+    /// The given number of packets of the frame were lost
+    Incomplete(u64),
 }
 
 pub trait DePayloader<M: MediaType>: Send + 'static {
+    /// Frames of this media type may be fragmented across multiple RTP packets (e.g. video).
+    ///
+    /// If set, the [`DePacketizer`] reassembles all packets belonging to a frame and passes them to
+    /// [`DePayloader::depayload_fragments`] instead of calling [`DePayloader::depayload`] for every packet.
+    const FRAGMENTED: bool = false;
+
     fn depayload(&mut self, payload: &[u8]) -> M::FrameData;
+
+    /// Depayload all fragments of a single frame, ordered by sequence number. Returns `None` to drop the frame.
+    ///
+    /// An incomplete frame is a good indicator to request a new keyframe from the sender.
+    ///
+    /// The default implementation drops incomplete frames and concatenates the payloads of complete ones.
+    fn depayload_fragments(
+        &mut self,
+        fragments: &[&[u8]],
+        result: FrameAssemblyResult,
+    ) -> Option<M::FrameData> {
+        if result != FrameAssemblyResult::Complete {
+            return None;
+        }
+
+        Some(self.depayload(&fragments.concat()))
+    }
 }
@@ -1,4 +1,5 @@
-use std::ops::Sub;
+use std::ops::{Add, Sub};
+use std::time::Duration;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct NtpTimestamp {
@@ -67,3 +68,23 @@ impl Sub for NtpTimestamp {
         self.inner - rhs.inner
     }
 }
+
+impl Add<Duration> for NtpTimestamp {
+    type Output = Self;
+
+    fn add(self, rhs: Duration) -> Self::Output {
+        Self {
+            inner: self.inner + rhs,
+        }
+    }
+}
+
+impl Sub<Duration> for NtpTimestamp {
+    type Output = Self;
+
+    fn sub(self, rhs: Duration) -> Self::Output {
+        Self {
+            inner: self.inner - rhs,
+        }
+    }
+}
@@ -1,13 +1,82 @@
-use crate::{Payloadable, Payloader, Rtp, RtpConfig, RtpConfigRange, RtpPacket};
+use crate::{
+    Payloadable, Payloader, RemoteReportReceiver, Rtp, RtpConfig, RtpConfigRange, RtpPacket,
+};
 use ezk::{ConfigRange, Frame, NextEventIsCancelSafe, Result, Source, SourceEvent, ValueRange};
-use std::collections::VecDeque;
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
 
 pub struct Packetizer<S: Source<MediaType: Payloadable>> {
     source: S,
     mtu: usize,
+    timestamp_mode: TimestampMode,
+    marker_bit_policy: MarkerBitPolicy,
+    remote_reports: Option<RemoteReportReceiver>,
+    continuation: Option<StreamPosition>,
     stream: Option<Stream<S::MediaType>>,
 }
 
+/// Sequence number and timestamp of the last packet created by a [`Packetizer`]
+///
+/// Used to continue the RTP stream with another packetizer, e.g. when switching to a codec with a different
+/// clock rate, see [`Packetizer::with_continuation`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamPosition {
+    pub sequence_number: u16,
+    pub timestamp: u32,
+    /// Capture time of the packet's frame, or the time the packet was created
+    pub instant: Instant,
+}
+
+/// Defines how the [`Packetizer`] assigns RTP timestamps to packets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimestampMode {
+    /// Use the timestamp of the frame for all packets created from it
+    FrameTimestamp,
+
+    /// Every frame advances the RTP timestamp by the given duration, converted into the
+    /// [RTP clock rate](Payloadable::RTP_CLOCK_RATE).
+    ///
+    /// Missing frames (e.g. suppressed silence) are detected using the frame timestamps and skipped in the RTP
+    /// timestamps.
+    FrameDuration(Duration),
+
+    /// Every packet advances the RTP timestamp by the given number of samples.
+    ///
+    /// Useful for audio codecs where a frame may be split into multiple packets. Missing frames are skipped in
+    /// the RTP timestamps, like with [`TimestampMode::FrameDuration`].
+    SamplesPerPacket(u32),
+
+    /// Derive the RTP timestamp from the wall clock time elapsed since the first frame, converted into the
+    /// [RTP clock rate](Payloadable::RTP_CLOCK_RATE).
+    ///
+    /// The time of a frame is its [capture time](Frame::capture_time), or the time it reached the packetizer if
+    /// it has none. When switching codecs the timestamps can be continued using [`Packetizer::with_continuation`].
+    ///
+    /// Useful for sources whose frame timestamps are unreliable or use a different clock rate than the RTP
+    /// payload format (e.g. G.722 which uses an 8000 Hz RTP clock for 16 kHz audio).
+    WallClock,
+}
+
+/// Defines when the [`Packetizer`] sets the marker bit of a packet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarkerBitPolicy {
+    /// Never set the marker bit
+    Never,
+
+    /// Set the marker bit on the first packet of a talk spurt (RFC 3551).
+    ///
+    /// A talk spurt starts with the first frame of the stream, a frame marked as [talk spurt](Frame::talk_spurt) or
+    /// when at least one frame is missing (e.g. because silence was suppressed). Missing frames are detected using
+    /// the frame timestamps and the frame length of the [`TimestampMode::FrameDuration`] and
+    /// [`TimestampMode::SamplesPerPacket`] modes.
+    TalkSpurt,
+
+    /// Set the marker bit on the last packet of every frame, as used by most video payload formats
+    EndOfFrame,
+}
+
 impl<S: Source<MediaType: Payloadable> + NextEventIsCancelSafe> NextEventIsCancelSafe
     for Packetizer<S>
 {
@@ -17,7 +86,21 @@ struct Stream<M: Payloadable> {
     config: RtpConfig,
     sequence_number: u16,
 
-    queue: VecDeque<RtpPacket>,
+    /// Timestamp of the next packet, when timestamps are not taken from the frames
+    next_timestamp: u32,
+    /// Added to the timestamps of all packets, to continue the timestamps of a previous stream
+    timestamp_offset: u32,
+    /// Previous stream to continue, applied when the first frame arrives
+    continuation: Option<StreamPosition>,
+    /// Last packet created
+    position: Option<StreamPosition>,
+    /// Arrival time of the first frame, used with [`TimestampMode::WallClock`]
+    wall_clock_start: Option<Instant>,
+    /// Timestamp of the last frame and its length in RTP timestamp units, if known
+    last_frame: Option<(u64, Option<u32>)>,
+
+    /// Packets and the capture time of the frame they were created from
+    queue: VecDeque<(RtpPacket, Option<Instant>)>,
     payloader: M::Payloader,
 }
 
@@ -29,6 +112,10 @@ where
         Self {
             source,
             mtu: 1400,
+            timestamp_mode: TimestampMode::FrameTimestamp,
+            marker_bit_policy: MarkerBitPolicy::Never,
+            remote_reports: None,
+            continuation: None,
             stream: None,
         }
     }
@@ -37,6 +124,64 @@ where
         self.mtu = mtu;
         self
     }
+
+    /// Set how RTP timestamps are generated, defaults to [`TimestampMode::FrameTimestamp`]
+    pub fn with_timestamp_mode(mut self, timestamp_mode: TimestampMode) -> Self {
+        self.timestamp_mode = timestamp_mode;
+        self
+    }
+
+    /// Set when the marker bit is set, defaults to [`MarkerBitPolicy::Never`]
+    pub fn with_marker_bit_policy(mut self, marker_bit_policy: MarkerBitPolicy) -> Self {
+        self.marker_bit_policy = marker_bit_policy;
+        self
+    }
+
+    /// Forward the reports of the remote receiver to the payloader, see [`Payloader::handle_remote_report`]
+    pub fn with_remote_reports(mut self, remote_reports: RemoteReportReceiver) -> Self {
+        self.remote_reports = Some(remote_reports);
+        self
+    }
+
+    /// Continue the sequence numbers and timestamps of a previous stream, e.g. after switching codecs.
+    ///
+    /// The timestamp of the first frame is extrapolated from the previous stream's last timestamp using the
+    /// elapsed time and the clock rate of this packetizer. The stream is also continued when this packetizer
+    /// is renegotiated.
+    pub fn with_continuation(mut self, position: StreamPosition) -> Self {
+        self.continuation = Some(position);
+        self
+    }
+
+    /// Returns the position of the last packet created, see [`Packetizer::with_continuation`]
+    pub fn position(&self) -> Option<StreamPosition> {
+        self.stream.as_ref()?.position
+    }
+}
+
+impl<M: Payloadable> Stream<M> {
+    /// Returns if a frame with the given timestamp starts a new talk spurt and the number of RTP timestamp units
+    /// missing between it and the previous frame
+    fn frame_gap(&self, timestamp: u64) -> (bool, u32) {
+        let Some((last_timestamp, last_length)) = self.last_frame else {
+            return (true, 0);
+        };
+
+        let Some(last_length) = last_length else {
+            return (false, 0);
+        };
+
+        let gap = timestamp
+            .checked_sub(last_timestamp)
+            .map_or(0, |delta| delta.saturating_sub(u64::from(last_length)));
+
+        // Only a completely missing frame is a gap, not small deviations of the frame timestamps
+        if gap >= u64::from(last_length.max(1)) {
+            (true, gap as u32)
+        } else {
+            (false, 0)
+        }
+    }
 }
 
 impl<S> Source for Packetizer<S>
@@ -69,9 +214,20 @@ where
 
         let config = RtpConfig { pt };
 
+        let continuation = match &self.stream {
+            Some(stream) => stream.position.or(stream.continuation),
+            None => self.continuation,
+        };
+
         self.stream = Some(Stream {
             config,
-            sequence_number: rand::random(),
+            sequence_number: continuation.map_or_else(rand::random, |p| p.sequence_number),
+            next_timestamp: rand::random(),
+            timestamp_offset: 0,
+            continuation,
+            position: None,
+            wall_clock_start: None,
+            last_frame: None,
             queue: VecDeque::new(),
             payloader: S::MediaType::make_payloader(config_),
         });
@@ -85,10 +241,12 @@ where
         };
 
         loop {
-            if let Some(packet) = stream.queue.pop_front() {
+            if let Some((packet, capture_time)) = stream.queue.pop_front() {
                 let timestamp = packet.get().timestamp();
 
-                return Ok(SourceEvent::Frame(Frame::new(packet, timestamp as u64)));
+                return Ok(SourceEvent::Frame(
+                    Frame::new(packet, timestamp as u64).with_capture_time(capture_time),
+                ));
             }
 
             let frame = match self.source.next_event().await? {
@@ -97,9 +255,70 @@ where
                 SourceEvent::RenegotiationNeeded => return Ok(SourceEvent::RenegotiationNeeded),
             };
 
-            let timestamp = (frame.timestamp & u64::from(u32::MAX)) as u32;
+            if let Some(report) = self.remote_reports.as_mut().and_then(|r| r.latest()) {
+                stream.payloader.handle_remote_report(&report);
+            }
+
+            let frame_timestamp = frame.timestamp;
+            let capture_time = frame.capture_time;
+            // Use the capture time if available, so processing delays don't end up in the timestamps
+            let frame_time = capture_time.unwrap_or_else(Instant::now);
+            let (talk_spurt, gap) = stream.frame_gap(frame_timestamp);
+            let talk_spurt = talk_spurt || frame.talk_spurt;
+
+            // Skip missing frames, so the receiver plays out the gap
+            stream.next_timestamp = stream.next_timestamp.wrapping_add(gap);
+
+            let frame_rtp_timestamp = match self.timestamp_mode {
+                TimestampMode::FrameTimestamp => (frame_timestamp & u64::from(u32::MAX)) as u32,
+                TimestampMode::FrameDuration(_) | TimestampMode::SamplesPerPacket(_) => {
+                    stream.next_timestamp
+                }
+                TimestampMode::WallClock => {
+                    let start = *stream.wall_clock_start.get_or_insert(frame_time);
+                    let elapsed = duration_to_rtp_timestamp::<S::MediaType>(
+                        frame_time.saturating_duration_since(start),
+                    );
+
+                    stream.next_timestamp.wrapping_add(elapsed)
+                }
+            };
+
+            if let Some(continuation) = stream.continuation.take() {
+                let elapsed = frame_time.saturating_duration_since(continuation.instant);
+                let elapsed = duration_to_rtp_timestamp::<S::MediaType>(elapsed);
+
+                stream.timestamp_offset = continuation
+                    .timestamp
+                    .wrapping_add(elapsed)
+                    .wrapping_sub(frame_rtp_timestamp);
+            }
+
+            let mut payloads = stream.payloader.payload(frame, self.mtu).peekable();
+            let mut first_packet = true;
+            let mut num_packets = 0u32;
+
+            while let Some(payload) = payloads.next() {
+                let last_packet = payloads.peek().is_none();
+
+                let timestamp = match self.timestamp_mode {
+                    TimestampMode::SamplesPerPacket(samples) => {
+                        let timestamp = stream.next_timestamp;
+                        stream.next_timestamp = stream.next_timestamp.wrapping_add(samples);
+                        timestamp
+                    }
+                    _ => frame_rtp_timestamp,
+                };
+                let timestamp = timestamp.wrapping_add(stream.timestamp_offset);
 
-            for payload in stream.payloader.payload(frame, self.mtu) {
+                let marker_bit = match self.marker_bit_policy {
+                    MarkerBitPolicy::Never => false,
+                    MarkerBitPolicy::TalkSpurt => first_packet && talk_spurt,
+                    MarkerBitPolicy::EndOfFrame => last_packet,
+                };
+
+                first_packet = false;
+                num_packets += 1;
                 stream.sequence_number = stream.sequence_number.wrapping_add(1);
 
                 let packet = RtpPacket::new(
@@ -107,11 +326,478 @@ where
                         .sequence_number(stream.sequence_number)
                         .timestamp(timestamp)
                         .payload_type(stream.config.pt)
+                        .marker_bit(marker_bit)
                         .payload(&payload),
                 );
 
-                stream.queue.push_back(packet);
+                stream.queue.push_back((packet, capture_time));
+                stream.position = Some(StreamPosition {
+                    sequence_number: stream.sequence_number,
+                    timestamp,
+                    instant: frame_time,
+                });
+            }
+
+            let frame_length = match self.timestamp_mode {
+                TimestampMode::FrameDuration(frame_duration) => {
+                    let frame_length = duration_to_rtp_timestamp::<S::MediaType>(frame_duration);
+                    stream.next_timestamp = stream.next_timestamp.wrapping_add(frame_length);
+                    Some(frame_length)
+                }
+                TimestampMode::SamplesPerPacket(samples) => Some(samples.wrapping_mul(num_packets)),
+                TimestampMode::FrameTimestamp | TimestampMode::WallClock => None,
+            };
+
+            stream.last_frame = Some((frame_timestamp, frame_length));
+        }
+    }
+}
+
+/// Convert a duration into units of the media type's RTP clock rate, wrapping like RTP timestamps
+fn duration_to_rtp_timestamp<M: Payloadable>(duration: Duration) -> u32 {
+    (duration.as_nanos() * u128::from(M::RTP_CLOCK_RATE) / 1_000_000_000) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DePayloader, RemoteReport, RtpSession};
+    use bytes::Bytes;
+    use ezk::{ConfigRange, MediaType};
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Debug)]
+    enum TestMedia<const CLOCK_RATE: u32 = 8000> {}
+
+    impl<const CLOCK_RATE: u32> MediaType for TestMedia<CLOCK_RATE> {
+        type ConfigRange = TestConfigRange;
+        type Config = TestConfig;
+        type FrameData = Bytes;
+    }
+
+    #[derive(Debug, Clone)]
+    struct TestConfigRange;
+
+    impl ConfigRange for TestConfigRange {
+        type Config = TestConfig;
+
+        fn any() -> Self {
+            Self
+        }
+
+        fn intersect(&self, _other: &Self) -> Option<Self> {
+            Some(Self)
+        }
+
+        fn contains(&self, _config: &Self::Config) -> bool {
+            true
+        }
+    }
+
+    #[derive(Debug, Clone, Default)]
+    struct TestConfig {
+        remote_reports: Arc<Mutex<Vec<RemoteReport>>>,
+    }
+
+    impl<const CLOCK_RATE: u32> Payloadable for TestMedia<CLOCK_RATE> {
+        type Payloader = TestPayloader;
+        type DePayloader = TestPayloader;
+
+        const STATIC_PT: Option<u8> = None;
+        const RTP_CLOCK_RATE: u32 = CLOCK_RATE;
+
+        fn make_payloader(config: Self::Config) -> Self::Payloader {
+            TestPayloader {
+                remote_reports: config.remote_reports,
             }
         }
+
+        fn make_depayloader(_: Vec<Self::ConfigRange>) -> (Self::Config, Self::DePayloader) {
+            let config = TestConfig::default();
+            let depayloader = Self::make_payloader(config.clone());
+            (config, depayloader)
+        }
+    }
+
+    struct TestPayloader {
+        remote_reports: Arc<Mutex<Vec<RemoteReport>>>,
+    }
+
+    impl<const CLOCK_RATE: u32> Payloader<TestMedia<CLOCK_RATE>> for TestPayloader {
+        fn payload(
+            &mut self,
+            frame: Frame<TestMedia<CLOCK_RATE>>,
+            max_size: usize,
+        ) -> impl Iterator<Item = Bytes> + '_ {
+            let data = frame.into_data();
+
+            (0..data.len())
+                .step_by(max_size)
+                .map(move |i| data.slice(i..data.len().min(i + max_size)))
+        }
+
+        fn handle_remote_report(&mut self, report: &RemoteReport) {
+            self.remote_reports.lock().unwrap().push(*report);
+        }
+    }
+
+    impl<const CLOCK_RATE: u32> DePayloader<TestMedia<CLOCK_RATE>> for TestPayloader {
+        fn depayload(&mut self, payload: &[u8]) -> Bytes {
+            Bytes::copy_from_slice(payload)
+        }
+    }
+
+    struct TestSource<const CLOCK_RATE: u32 = 8000> {
+        config: TestConfig,
+        frames: VecDeque<Frame<TestMedia<CLOCK_RATE>>>,
+    }
+
+    impl<const CLOCK_RATE: u32> TestSource<CLOCK_RATE> {
+        fn new(frames: impl IntoIterator<Item = Frame<TestMedia<CLOCK_RATE>>>) -> Self {
+            Self {
+                config: TestConfig::default(),
+                frames: frames.into_iter().collect(),
+            }
+        }
+    }
+
+    impl<const CLOCK_RATE: u32> Source for TestSource<CLOCK_RATE> {
+        type MediaType = TestMedia<CLOCK_RATE>;
+
+        async fn capabilities(&mut self) -> Result<Vec<TestConfigRange>> {
+            Ok(vec![TestConfigRange])
+        }
+
+        async fn negotiate_config(
+            &mut self,
+            _available: Vec<TestConfigRange>,
+        ) -> Result<TestConfig> {
+            Ok(self.config.clone())
+        }
+
+        async fn next_event(&mut self) -> Result<SourceEvent<TestMedia<CLOCK_RATE>>> {
+            match self.frames.pop_front() {
+                Some(frame) => Ok(SourceEvent::Frame(frame)),
+                None => Ok(SourceEvent::EndOfData),
+            }
+        }
+    }
+
+    fn frame<const CLOCK_RATE: u32>(timestamp: u64, len: usize) -> Frame<TestMedia<CLOCK_RATE>> {
+        Frame::new(Bytes::from(vec![0u8; len]), timestamp)
+    }
+
+    async fn negotiate<const CLOCK_RATE: u32>(packetizer: &mut Packetizer<TestSource<CLOCK_RATE>>) {
+        packetizer
+            .negotiate_config(vec![RtpConfigRange {
+                pt: ValueRange::Value(96),
+            }])
+            .await
+            .unwrap();
+    }
+
+    async fn collect_packets<const CLOCK_RATE: u32>(
+        packetizer: &mut Packetizer<TestSource<CLOCK_RATE>>,
+    ) -> Vec<RtpPacket> {
+        let mut packets = vec![];
+
+        while let SourceEvent::Frame(frame) = packetizer.next_event().await.unwrap() {
+            packets.push(frame.into_data());
+        }
+
+        packets
+    }
+
+    #[tokio::test]
+    async fn forward_remote_reports() {
+        use rtcp_types::{
+            CompoundBuilder, ReceiverReport, ReportBlock, RtcpPacketParser, RtcpPacketWriterExt,
+        };
+
+        let now = Instant::now();
+        let mut session = RtpSession::new(now, crate::NtpTimestamp::now(), 1, 8000);
+
+        let source = TestSource::<8000>::new([frame(0, 160), frame(160, 160)]);
+        let remote_reports = source.config.remote_reports.clone();
+
+        let mut packetizer =
+            Packetizer::new(source).with_remote_reports(session.subscribe_remote_reports());
+
+        let rr =
+            ReceiverReport::builder(2).add_report_block(ReportBlock::builder(1).fraction_lost(128));
+        let mut buf = vec![0u8; 1500];
+        let len = CompoundBuilder::default()
+            .add_packet(rr)
+            .write_into(&mut buf)
+            .unwrap();
+        session.recv_rtcp(now, rtcp_types::Packet::parse(&buf[..len]).unwrap());
+
+        negotiate(&mut packetizer).await;
+        let packets = collect_packets(&mut packetizer).await;
+        assert_eq!(packets.len(), 2);
+
+        // The report is forwarded once
+        let remote_reports = remote_reports.lock().unwrap();
+        assert_eq!(remote_reports.len(), 1);
+        assert_eq!(remote_reports[0].reporter_ssrc, 2);
+        assert_eq!(remote_reports[0].fraction_lost, 0.5);
+    }
+
+    #[tokio::test]
+    async fn continue_stream_with_other_clock_rate() {
+        let mut packetizer =
+            Packetizer::new(TestSource::<8000>::new([frame(0, 160), frame(160, 160)]));
+        negotiate(&mut packetizer).await;
+        let packets = collect_packets(&mut packetizer).await;
+        let last = packets.last().unwrap().get();
+
+        let position = packetizer.position().unwrap();
+        assert_eq!(position.sequence_number, last.sequence_number());
+        assert_eq!(position.timestamp, last.timestamp());
+
+        let mut packetizer = Packetizer::new(TestSource::<16000>::new([
+            frame(5000, 320),
+            frame(5320, 320),
+        ]))
+        .with_continuation(position);
+        negotiate(&mut packetizer).await;
+        let packets = collect_packets(&mut packetizer).await;
+
+        let first = packets[0].get();
+        assert_eq!(
+            first.sequence_number(),
+            position.sequence_number.wrapping_add(1)
+        );
+
+        // Only the time elapsed since the last packet was added
+        let elapsed = first.timestamp().wrapping_sub(position.timestamp);
+        assert!(elapsed < 16000, "{elapsed}");
+        assert_eq!(
+            packets[1].get().timestamp(),
+            first.timestamp().wrapping_add(320)
+        );
+    }
+
+    #[tokio::test]
+    async fn renegotiation_continues_stream() {
+        let mut packetizer =
+            Packetizer::new(TestSource::<8000>::new([frame(0, 160), frame(160, 160)]));
+        negotiate(&mut packetizer).await;
+
+        let SourceEvent::Frame(first) = packetizer.next_event().await.unwrap() else {
+            panic!("expected packet");
+        };
+        let first = first.into_data();
+
+        negotiate(&mut packetizer).await;
+        let packets = collect_packets(&mut packetizer).await;
+        let second = packets[0].get();
+
+        assert_eq!(
+            second.sequence_number(),
+            first.get().sequence_number().wrapping_add(1)
+        );
+        assert!(second.timestamp().wrapping_sub(first.get().timestamp()) < 8000);
+    }
+
+    #[tokio::test]
+    async fn frame_duration_uses_rtp_clock_rate() {
+        let mut packetizer = Packetizer::new(TestSource::<16000>::new([
+            frame(0, 10),
+            frame(1, 10),
+            frame(2, 10),
+        ]))
+        .with_timestamp_mode(TimestampMode::FrameDuration(Duration::from_millis(20)));
+        negotiate(&mut packetizer).await;
+
+        let timestamps: Vec<u32> = collect_packets(&mut packetizer)
+            .await
+            .iter()
+            .map(|packet| packet.get().timestamp())
+            .collect();
+
+        assert_eq!(timestamps[1].wrapping_sub(timestamps[0]), 320);
+        assert_eq!(timestamps[2].wrapping_sub(timestamps[1]), 320);
+    }
+
+    #[tokio::test]
+    async fn wall_clock_uses_capture_time() {
+        let start = Instant::now();
+        let captured_frame = |timestamp, capture_time| {
+            frame(timestamp, 10).with_capture_time(Some(start + capture_time))
+        };
+
+        let mut packetizer = Packetizer::new(TestSource::<8000>::new([
+            captured_frame(0, Duration::ZERO),
+            captured_frame(0, Duration::from_millis(20)),
+            captured_frame(0, Duration::from_millis(60)),
+        ]))
+        .with_timestamp_mode(TimestampMode::WallClock);
+        negotiate(&mut packetizer).await;
+
+        let timestamps: Vec<u32> = collect_packets(&mut packetizer)
+            .await
+            .iter()
+            .map(|packet| packet.get().timestamp())
+            .collect();
+
+        assert_eq!(timestamps[1].wrapping_sub(timestamps[0]), 160);
+        assert_eq!(timestamps[2].wrapping_sub(timestamps[0]), 480);
+    }
+
+    #[tokio::test]
+    async fn wall_clock_continues_across_clock_rates() {
+        let start = Instant::now();
+
+        let mut packetizer = Packetizer::new(TestSource::<8000>::new([
+            frame(0, 10).with_capture_time(Some(start)),
+            frame(0, 10).with_capture_time(Some(start + Duration::from_millis(20))),
+        ]))
+        .with_timestamp_mode(TimestampMode::WallClock);
+        negotiate(&mut packetizer).await;
+        collect_packets(&mut packetizer).await;
+        let position = packetizer.position().unwrap();
+
+        // Switch to a 16kHz codec
+        let mut packetizer = Packetizer::new(TestSource::<16000>::new([
+            frame(0, 10).with_capture_time(Some(start + Duration::from_millis(40))),
+            frame(0, 10).with_capture_time(Some(start + Duration::from_millis(60))),
+        ]))
+        .with_timestamp_mode(TimestampMode::WallClock)
+        .with_continuation(position);
+        negotiate(&mut packetizer).await;
+
+        let timestamps: Vec<u32> = collect_packets(&mut packetizer)
+            .await
+            .iter()
+            .map(|packet| packet.get().timestamp())
+            .collect();
+
+        assert_eq!(timestamps[0], position.timestamp.wrapping_add(320));
+        assert_eq!(timestamps[1], position.timestamp.wrapping_add(640));
+    }
+
+    fn timestamps_and_markers(packets: &[RtpPacket]) -> Vec<(u32, bool)> {
+        let first_timestamp = packets[0].get().timestamp();
+
+        packets
+            .iter()
+            .map(|packet| {
+                let packet = packet.get();
+                (
+                    packet.timestamp().wrapping_sub(first_timestamp),
+                    packet.marker_bit(),
+                )
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn frame_timestamp_mode() {
+        let mut packetizer =
+            Packetizer::new(TestSource::<8000>::new([frame(1000, 10), frame(1160, 10)]));
+        negotiate(&mut packetizer).await;
+
+        let packets = collect_packets(&mut packetizer).await;
+        assert_eq!(packets[0].get().timestamp(), 1000);
+        assert_eq!(packets[1].get().timestamp(), 1160);
+        assert!(!packets[0].get().marker_bit());
+    }
+
+    #[tokio::test]
+    async fn talk_spurts_with_frame_duration() {
+        // 20ms frames, silence suppressed after the 2nd and 3rd frame
+        let mut packetizer = Packetizer::new(TestSource::<8000>::new([
+            frame(0, 10),
+            frame(160, 10),
+            frame(640, 10),
+            frame(1120, 10),
+            frame(1280, 10),
+        ]))
+        .with_timestamp_mode(TimestampMode::FrameDuration(Duration::from_millis(20)))
+        .with_marker_bit_policy(MarkerBitPolicy::TalkSpurt);
+        negotiate(&mut packetizer).await;
+
+        let packets = collect_packets(&mut packetizer).await;
+
+        assert_eq!(
+            timestamps_and_markers(&packets),
+            [
+                (0, true),
+                (160, false),
+                (640, true),
+                (1120, true),
+                (1280, false)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn talk_spurts_with_varying_frame_sizes() {
+        // Frames of 1 or 2 packets without any gaps
+        let mut packetizer = Packetizer::new(TestSource::<8000>::new([
+            frame(0, 160),
+            frame(160, 320),
+            frame(480, 160),
+            frame(640, 320),
+            // 2 frames missing
+            frame(1280, 160),
+        ]))
+        .with_mtu(160)
+        .with_timestamp_mode(TimestampMode::SamplesPerPacket(160))
+        .with_marker_bit_policy(MarkerBitPolicy::TalkSpurt);
+        negotiate(&mut packetizer).await;
+
+        let packets = collect_packets(&mut packetizer).await;
+
+        assert_eq!(
+            timestamps_and_markers(&packets),
+            [
+                (0, true),
+                (160, false),
+                (320, false),
+                (480, false),
+                (640, false),
+                (800, false),
+                (1280, true)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn end_of_frame_marker() {
+        let mut packetizer =
+            Packetizer::new(TestSource::<90000>::new([frame(0, 300), frame(3000, 100)]))
+                .with_mtu(100)
+                .with_marker_bit_policy(MarkerBitPolicy::EndOfFrame);
+        negotiate(&mut packetizer).await;
+
+        let packets = collect_packets(&mut packetizer).await;
+
+        assert_eq!(
+            timestamps_and_markers(&packets),
+            [(0, false), (0, false), (0, true), (3000, true)]
+        );
+    }
+
+    #[tokio::test]
+    async fn talk_spurt_flag() {
+        // Comfort noise between the talk spurts, so there are no gaps in the frame timestamps
+        let mut packetizer = Packetizer::new(TestSource::<8000>::new([
+            frame(0, 10),
+            frame(160, 10),
+            frame(320, 10).with_talk_spurt(true),
+            frame(480, 10),
+        ]))
+        .with_timestamp_mode(TimestampMode::FrameDuration(Duration::from_millis(20)))
+        .with_marker_bit_policy(MarkerBitPolicy::TalkSpurt);
+        negotiate(&mut packetizer).await;
+
+        let packets = collect_packets(&mut packetizer).await;
+
+        assert_eq!(
+            timestamps_and_markers(&packets),
+            [(0, true), (160, false), (320, true), (480, false)]
+        );
     }
 }
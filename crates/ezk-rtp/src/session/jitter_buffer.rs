@@ -4,6 +4,11 @@ use std::{
     collections::{btree_map::Entry, BTreeMap},
 };
 
+/// Maximum forward jump of the sequence number before it is considered a restart of the stream (RFC 3550 A.1)
+const MAX_DROPOUT: u64 = 3000;
+/// Maximum backward jump of the sequence number before it is considered a restart of the stream (RFC 3550 A.1)
+const MAX_MISORDER: u64 = 100;
+
 #[derive(Debug)]
 pub(crate) struct JitterBuffer {
     /// maximum number of entries
@@ -20,6 +25,9 @@ pub(crate) struct JitterBuffer {
     pub(crate) received: u64,
     /// num packets not received
     pub(crate) lost: u64,
+
+    /// num detected restarts of the sequence numbers
+    pub(crate) resets: u64,
 }
 
 impl Default for JitterBuffer {
@@ -31,6 +39,7 @@ impl Default for JitterBuffer {
             dropped: 0,
             received: 0,
             lost: 0,
+            resets: 0,
         }
     }
 }
@@ -44,6 +53,9 @@ struct State {
 
     /// last known timestamp
     last_timestamp: u64,
+
+    /// Packet with an unexpected sequence number, if the next packet follows it the stream has been restarted
+    probable_reset: Option<RtpPacket>,
 }
 
 #[derive(Debug)]
@@ -57,13 +69,29 @@ impl JitterBuffer {
         self.state.as_ref().map(|s| s.head)
     }
 
-    pub(crate) fn push(&mut self, packet: RtpPacket) {
-        let rtp_packet = packet.get();
+    /// Returns if the last pushed packet had an unexpected sequence number and is held until the next packet
+    /// confirms or refutes a restart of the stream
+    pub(crate) fn has_probable_reset(&self) -> bool {
+        self.state
+            .as_ref()
+            .is_some_and(|state| state.probable_reset.is_some())
+    }
+
+    /// Push a packet into the buffer.
+    ///
+    /// Returns `true` if the packet revealed a restart of the stream's sequence numbers, in which case all previously
+    /// buffered packets have been discarded.
+    pub(crate) fn push(&mut self, packet: RtpPacket) -> bool {
+        let (sequence_number, timestamp) = {
+            let rtp_packet = packet.get();
+            (rtp_packet.sequence_number(), rtp_packet.timestamp())
+        };
 
         let Some(state) = &mut self.state else {
-            let sequence_number = u64::from(rtp_packet.sequence_number());
-            let timestamp = u64::from(rtp_packet.timestamp());
+            let sequence_number = u64::from(sequence_number);
+            let timestamp = u64::from(timestamp);
 
+            self.received += 1;
             self.entries
                 .insert(sequence_number, JbEntry { timestamp, packet });
 
@@ -71,18 +99,46 @@ impl JitterBuffer {
                 head: sequence_number,
                 tail: sequence_number,
                 last_timestamp: timestamp,
+                probable_reset: None,
             });
 
-            return;
+            return false;
         };
 
-        let sequence_number = guess_sequence_number(state.tail, rtp_packet.sequence_number());
-        let timestamp = guess_timestamp(state.last_timestamp, rtp_packet.timestamp());
+        let extended_sequence_number = guess_sequence_number(state.tail, sequence_number);
+
+        if extended_sequence_number > state.head + MAX_DROPOUT
+            || extended_sequence_number + MAX_MISORDER < state.tail
+        {
+            match state.probable_reset.take() {
+                Some(previous)
+                    if previous.get().sequence_number().wrapping_add(1) == sequence_number =>
+                {
+                    // Two sequential packets far outside the expected range, the sender restarted its stream
+                    self.reset(previous, packet);
+                    return true;
+                }
+                Some(_) => {
+                    self.dropped += 1;
+                    state.probable_reset = Some(packet);
+                }
+                None => state.probable_reset = Some(packet),
+            }
+
+            return false;
+        }
+
+        if state.probable_reset.take().is_some() {
+            self.dropped += 1;
+        }
+
+        let sequence_number = extended_sequence_number;
+        let timestamp = guess_timestamp(state.last_timestamp, timestamp);
         state.last_timestamp = timestamp;
 
         if sequence_number < state.tail {
             self.dropped += 1;
-            return;
+            return false;
         }
 
         if let Entry::Vacant(entry) = self.entries.entry(sequence_number) {
@@ -93,6 +149,18 @@ impl JitterBuffer {
         state.head = cmp::max(state.head, sequence_number);
 
         self.ensure_max_size();
+
+        false
+    }
+
+    fn reset(&mut self, first: RtpPacket, second: RtpPacket) {
+        self.resets += 1;
+        self.dropped += self.entries.len() as u64;
+        self.entries.clear();
+        self.state = None;
+
+        self.push(first);
+        self.push(second);
     }
 
     fn ensure_max_size(&mut self) {
@@ -129,25 +197,24 @@ impl JitterBuffer {
     }
 }
 
-fn guess_sequence_number(reference: u64, got: u16) -> u64 {
-    wrapping_counter_to_u64_counter(reference, u64::from(got), u64::from(u16::MAX))
+pub(crate) fn guess_sequence_number(reference: u64, got: u16) -> u64 {
+    wrapping_counter_to_u64_counter(reference, u64::from(got), 1 << 16)
 }
 
 pub(crate) fn guess_timestamp(reference: u64, got: u32) -> u64 {
-    wrapping_counter_to_u64_counter(reference, u64::from(got), u64::from(u32::MAX))
+    wrapping_counter_to_u64_counter(reference, u64::from(got), 1 << 32)
 }
 
-fn wrapping_counter_to_u64_counter(reference: u64, got: u64, max: u64) -> u64 {
-    let mul = (reference / max).saturating_sub(1);
-
-    let low = mul * max + got;
-    let high = (mul + 1) * max + got;
+/// Extend the wrapping counter value `got` to the value closest to `reference`
+fn wrapping_counter_to_u64_counter(reference: u64, got: u64, modulus: u64) -> u64 {
+    let cycle = reference / modulus;
 
-    if low.abs_diff(reference) < high.abs_diff(reference) {
-        low
-    } else {
-        high
-    }
+    [cycle.checked_sub(1), Some(cycle), Some(cycle + 1)]
+        .into_iter()
+        .flatten()
+        .map(|cycle| cycle * modulus + got)
+        .min_by_key(|candidate| candidate.abs_diff(reference))
+        .expect("at least one candidate")
 }
 
 #[cfg(test)]
@@ -183,14 +250,53 @@ mod tests {
     fn sequence_number_guessing() {
         assert_eq!(guess_sequence_number(0, 0), 0);
         assert_eq!(guess_sequence_number(1, 65535), 65535);
-        assert_eq!(guess_sequence_number(65536, 1), 65536);
-        assert_eq!(guess_sequence_number(65534, 1), 65536);
-        assert_eq!(guess_sequence_number(u16::MAX as u64 * 2 + 1, 1), 131071);
+        assert_eq!(guess_sequence_number(65536, 1), 65537);
+        assert_eq!(guess_sequence_number(65534, 1), 65537);
+        assert_eq!(guess_sequence_number(65535, 0), 65536);
+        assert_eq!(guess_sequence_number(u16::MAX as u64 * 2 + 1, 1), 131073);
         assert_eq!(guess_sequence_number(65535, 65534), 65534);
         assert_eq!(guess_sequence_number(65534, 65534), 65534);
         assert_eq!(
             guess_sequence_number(u16::MAX as u64 * 2 + 1, 65534),
-            131069
+            131070
         );
+        assert_eq!(guess_timestamp(u64::from(u32::MAX), 10), 1 << 32 | 10);
+    }
+
+    #[test]
+    fn sequence_number_rollover() {
+        let mut jb = JitterBuffer::default();
+
+        jb.push(make_packet(65534, 100));
+        jb.push(make_packet(65535, 200));
+        jb.push(make_packet(0, 300));
+
+        assert_eq!(jb.pop(1000).unwrap().get().sequence_number(), 65534);
+        assert_eq!(jb.pop(1000).unwrap().get().sequence_number(), 65535);
+        assert_eq!(jb.pop(1000).unwrap().get().sequence_number(), 0);
+        assert_eq!(jb.lost, 0);
+        assert_eq!(jb.last_sequence_number(), Some(65536));
+    }
+
+    #[test]
+    fn sequence_number_reset() {
+        let mut jb = JitterBuffer::default();
+
+        assert!(!jb.push(make_packet(20000, 100)));
+        assert!(!jb.push(make_packet(20001, 200)));
+
+        // single stray packet is ignored
+        assert!(!jb.push(make_packet(5, 300)));
+        assert!(!jb.push(make_packet(20002, 300)));
+        assert_eq!(jb.dropped, 1);
+
+        // two sequential packets restart the stream
+        assert!(!jb.push(make_packet(10, 400)));
+        assert!(jb.push(make_packet(11, 500)));
+        assert_eq!(jb.resets, 1);
+
+        assert_eq!(jb.pop(1000).unwrap().get().sequence_number(), 10);
+        assert_eq!(jb.pop(1000).unwrap().get().sequence_number(), 11);
+        assert!(jb.pop(1000).is_none());
     }
 }
@@ -1,29 +1,173 @@
-use crate::{NtpTimestamp, RtpPacket};
+use crate::{NtpTimestamp, Rtp, RtpPacket};
+use ezk::Frame;
 use jitter_buffer::{guess_timestamp, JitterBuffer};
 use rtcp_types::{
-    CompoundBuilder, ReceiverReport, ReportBlock, RtcpPacketWriterExt, RtcpWriteError, SdesBuilder,
-    SdesChunkBuilder, SdesItemBuilder, SenderReport,
+    Bye, CompoundBuilder, ReceiverReport, ReportBlock, RtcpPacketWriterExt, RtcpWriteError,
+    SdesBuilder, SdesChunkBuilder, SdesItemBuilder, SenderReport,
+};
+use stats::StatsHistory;
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
 };
-use std::time::{Duration, Instant};
 use time::ext::InstantExt;
+use tokio::sync::watch;
 
 mod jitter_buffer;
+mod stats;
+
+pub use stats::StatsSample;
+
+pub(crate) use jitter_buffer::guess_sequence_number;
 
 const DEFAULT_JITTERBUFFER_LENGTH: Duration = Duration::from_millis(100);
 
+/// Maximum number of unread [`RemoteReport`]s kept by the session
+const MAX_PENDING_REMOTE_REPORTS: usize = 64;
+
+/// Maximum number of remote sources tracked by the session
+const MAX_RECEIVERS: usize = 4096;
+
+/// Difference between the arrival time and timestamp of two packets, after which the timestamps are considered
+/// discontinuous (e.g. after a hold) and not used to calculate the jitter
+const MAX_TIMESTAMP_DISCONTINUITY: Duration = Duration::from_secs(10);
+
+/// Maximum number of new remote sources in probation, the oldest one is discarded when exceeded
+const MAX_PROBATION_SOURCES: usize = 64;
+
+/// RTCP SDES item type of the canonical end-point identifier (CNAME), see RFC 3550
+pub const SDES_ITEM_CNAME: u8 = 1;
+
+/// RTCP SDES item type of the media identification (MID), see RFC 8843
+pub const SDES_ITEM_MID: u8 = 15;
+
 /// Single RTP session, (1 sender, many receiver)
 ///
 /// This can be used to publish a single RTP source and receive others.
 /// It manages a jitterbuffer for every remote ssrc and can generate RTCP reports.
+///
+/// The session never reads the clock by itself, all time dependent methods take the current time as `now`
+/// parameter. NTP timestamps used in RTCP are derived from `now`, relative to the session's creation.
 pub struct RtpSession {
     ssrc: u32,
     clock_rate: u32,
 
+    /// Wall clock time at the creation of the session
+    ntp_reference: (Instant, NtpTimestamp),
+
     /// tag/type, prefix, value
     source_description_items: Vec<(u8, Option<Vec<u8>>, String)>,
 
     sender: Option<SenderState>,
     receiver: Vec<ReceiverState>,
+
+    /// Jitter buffer length used when none is passed to [`RtpSession::pop_rtp`]
+    jitter_buffer_length: Duration,
+    /// Duration of a single media frame, if known
+    frame_duration: Option<Duration>,
+
+    /// Number of sequential packets required to accept a new source
+    probation: u16,
+    probation_sources: VecDeque<ProbationSource>,
+
+    /// Only accept RTP packets with these payload types, if set
+    accepted_payload_types: Option<Vec<u8>>,
+    /// Only accept RTP packets from these ssrcs, if set
+    accepted_ssrcs: Option<Vec<u32>>,
+
+    /// Remove receivers which haven't sent any RTP packets for this duration
+    receiver_timeout: Option<Duration>,
+    timed_out_receivers: VecDeque<u32>,
+
+    /// Remote sources which restarted their stream
+    stream_resets: VecDeque<u32>,
+
+    remote_reports: VecDeque<RemoteReport>,
+    remote_reports_tx: watch::Sender<Option<RemoteReport>>,
+
+    /// Items announced by remote sources in RTCP SDES packets
+    remote_source_descriptions: HashMap<u32, RemoteSourceDescription>,
+
+    stats: StatsHistory,
+}
+
+/// Reception quality of the local stream, as reported by a remote receiver
+///
+/// Retrieved using [`RtpSession::pop_remote_report`] or delivered to the sending pipeline using a
+/// [`RemoteReportReceiver`], so encoders can adapt bitrate or FEC to the network conditions seen by the peer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RemoteReport {
+    /// SSRC of the remote receiver that sent the report
+    pub reporter_ssrc: u32,
+
+    /// Fraction of packets lost since the previous report, in the range `0.0..=1.0`
+    pub fraction_lost: f32,
+
+    /// Total number of packets lost since the beginning of reception
+    pub cumulative_lost: u32,
+
+    /// Interarrival jitter in RTP timestamp units
+    pub jitter: u32,
+
+    /// Round trip time derived from the report's LSR and DLSR fields.
+    ///
+    /// `None` if the remote hasn't received a sender report from this session yet.
+    pub rtt: Option<Duration>,
+}
+
+/// Receives the latest [`RemoteReport`] of an [`RtpSession`], see [`RtpSession::subscribe_remote_reports`]
+///
+/// Can be passed to the sending pipeline's [`Packetizer`](crate::Packetizer) which forwards the reports to its
+/// [`Payloader`](crate::Payloader), or to encoders directly.
+#[derive(Debug, Clone)]
+pub struct RemoteReportReceiver {
+    rx: watch::Receiver<Option<RemoteReport>>,
+}
+
+impl RemoteReportReceiver {
+    /// Returns the latest report, if it hasn't been returned by this receiver before
+    pub fn latest(&mut self) -> Option<RemoteReport> {
+        if !self.rx.has_changed().unwrap_or(false) {
+            return None;
+        }
+
+        *self.rx.borrow_and_update()
+    }
+
+    /// Wait for the next report, returns `None` once the session has been dropped
+    pub async fn changed(&mut self) -> Option<RemoteReport> {
+        self.rx.changed().await.ok()?;
+        *self.rx.borrow_and_update()
+    }
+}
+
+/// Mapping of a remote source's RTP timestamps to its wall clock, taken from its latest sender report
+///
+/// Streams of the same remote endpoint (sharing a CNAME, see [`RtpSession::remote_cname`]) can be synchronized
+/// for playback (e.g. lip-sync of audio and video) by converting their RTP timestamps into the common wall clock.
+/// [`RtpSession::pop_rtp_frame`] applies this mapping to the received packets.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SyncInfo {
+    /// Wall clock time of the sender when the sender report was created
+    pub ntp_timestamp: NtpTimestamp,
+    /// RTP timestamp corresponding to `ntp_timestamp`
+    pub rtp_timestamp: u32,
+    pub clock_rate: u32,
+}
+
+impl SyncInfo {
+    /// Convert an RTP timestamp of the source into the sender's wall clock
+    pub fn to_ntp_timestamp(&self, rtp_timestamp: u32) -> NtpTimestamp {
+        let delta = rtp_timestamp.wrapping_sub(self.rtp_timestamp) as i32;
+        let offset =
+            Duration::from_secs_f64(f64::from(delta.unsigned_abs()) / f64::from(self.clock_rate));
+
+        if delta >= 0 {
+            self.ntp_timestamp + offset
+        } else {
+            self.ntp_timestamp - offset
+        }
+    }
 }
 
 struct SenderState {
@@ -43,21 +187,203 @@ struct ReceiverState {
     last_rtp_received: Option<(Instant, u64)>,
     jitter: f32,
 
-    last_sr: Option<NtpTimestamp>,
+    /// Set when the clock rate changed, the next packet must not be used to calculate the jitter
+    restart_jitter: bool,
+
+    last_sr: Option<ReceivedSenderReport>,
     total_lost: u64,
 }
 
+struct ReceivedSenderReport {
+    /// Local time the sender report was received
+    received_at: NtpTimestamp,
+
+    /// Sender's NTP and RTP timestamp contained in the report
+    ntp_timestamp: NtpTimestamp,
+    rtp_timestamp: u32,
+}
+
+/// New remote source which hasn't sent enough sequential packets yet
+struct ProbationSource {
+    ssrc: u32,
+    packets: Vec<(Instant, RtpPacket)>,
+    /// Sender report received during the probation, applied once the source is accepted
+    last_sr: Option<ReceivedSenderReport>,
+}
+
+#[derive(Default)]
+struct RemoteSourceDescription {
+    cname: Option<String>,
+    mid: Option<String>,
+}
+
 impl RtpSession {
-    pub fn new(ssrc: u32, clock_rate: u32) -> Self {
+    /// Create a new session.
+    ///
+    /// `now` and `ntp_now` must describe the same point in time (e.g. `Instant::now()` and `NtpTimestamp::now()`),
+    /// they are used to derive the NTP timestamps of RTCP packets from the `now` passed to the other methods.
+    pub fn new(now: Instant, ntp_now: NtpTimestamp, ssrc: u32, clock_rate: u32) -> Self {
         Self {
             ssrc,
             source_description_items: vec![],
             clock_rate,
+            ntp_reference: (now, ntp_now),
             sender: None,
             receiver: vec![],
+            jitter_buffer_length: DEFAULT_JITTERBUFFER_LENGTH,
+            frame_duration: None,
+            probation: 0,
+            probation_sources: VecDeque::new(),
+            accepted_payload_types: None,
+            accepted_ssrcs: None,
+            receiver_timeout: None,
+            timed_out_receivers: VecDeque::new(),
+            stream_resets: VecDeque::new(),
+            remote_reports: VecDeque::new(),
+            remote_reports_tx: watch::channel(None).0,
+            remote_source_descriptions: HashMap::new(),
+            stats: StatsHistory::default(),
         }
     }
 
+    /// Configure the statistics history, see [`RtpSession::stats_history`].
+    ///
+    /// Discards all previously collected samples. Defaults to 60 samples of 1 second each.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `interval` is zero
+    pub fn with_stats_history(mut self, interval: Duration, len: usize) -> Self {
+        self.stats = StatsHistory::new(interval, len);
+        self
+    }
+
+    /// Remove the state of remote sources that haven't sent any RTP packets for the given duration.
+    ///
+    /// Removed sources can be retrieved using [`RtpSession::pop_timed_out_receiver`]. Disabled by default.
+    pub fn with_receiver_timeout(mut self, timeout: Duration) -> Self {
+        self.set_receiver_timeout(Some(timeout));
+        self
+    }
+
+    /// Set or disable the receiver timeout, see [`RtpSession::with_receiver_timeout`]
+    pub fn set_receiver_timeout(&mut self, timeout: Option<Duration>) {
+        self.receiver_timeout = timeout;
+    }
+
+    /// Set the default jitter buffer length, defaults to 100ms
+    pub fn with_jitter_buffer_length(mut self, length: Duration) -> Self {
+        self.jitter_buffer_length = length;
+        self
+    }
+
+    /// Size the default jitter buffer in frames of the given duration (e.g. the packet time of an audio codec).
+    ///
+    /// Codecs with long frames (e.g. 60ms Opus) need a deeper buffer than 100ms to absorb the same number of
+    /// late packets as 20ms telephony codecs. The length saturates at [`Duration::MAX`].
+    pub fn with_jitter_buffer_frames(mut self, frames: u32, frame_duration: Duration) -> Self {
+        self.frame_duration = Some(frame_duration);
+        self.jitter_buffer_length = frame_duration.checked_mul(frames).unwrap_or(Duration::MAX);
+        self
+    }
+
+    /// Default jitter buffer length used by [`RtpSession::pop_rtp`]
+    pub fn jitter_buffer_length(&self) -> Duration {
+        self.jitter_buffer_length
+    }
+
+    /// Default jitter buffer length in frames, if the frame duration is known.
+    ///
+    /// See [`RtpSession::with_jitter_buffer_frames`].
+    pub fn jitter_buffer_frames(&self) -> Option<u32> {
+        let frame_duration = self.frame_duration.filter(|d| !d.is_zero())?;
+
+        let frames = self
+            .jitter_buffer_length
+            .as_nanos()
+            .div_ceil(frame_duration.as_nanos());
+
+        Some(u32::try_from(frames).unwrap_or(u32::MAX))
+    }
+
+    /// Only accept new remote sources after receiving the given number of packets with sequential sequence numbers.
+    ///
+    /// This avoids creating state for random or scanning traffic, RFC 3550 suggests 2. Packets received during the
+    /// probation are kept and passed on once the source is accepted, packets of sources failing it are counted in
+    /// [`StatsSample::rejected_packets`]. Disabled by default.
+    pub fn with_probation(mut self, packets: u16) -> Self {
+        self.probation = packets;
+        self
+    }
+
+    /// Drop all received RTP packets with a payload type not contained in `payload_types`.
+    ///
+    /// Rejected packets are counted in [`StatsSample::rejected_packets`]. By default all payload types are accepted.
+    pub fn with_accepted_payload_types(
+        mut self,
+        payload_types: impl IntoIterator<Item = u8>,
+    ) -> Self {
+        self.set_accepted_payload_types(Some(payload_types.into_iter().collect()));
+        self
+    }
+
+    /// Set or remove the payload type filter, see [`RtpSession::with_accepted_payload_types`]
+    ///
+    /// Packets of sources in probation which are no longer accepted are discarded.
+    pub fn set_accepted_payload_types(&mut self, payload_types: Option<Vec<u8>>) {
+        if let Some(payload_types) = &payload_types {
+            let mut rejected = 0;
+
+            for source in &mut self.probation_sources {
+                let len = source.packets.len();
+                source
+                    .packets
+                    .retain(|(_, packet)| payload_types.contains(&packet.get().payload_type()));
+                rejected += len - source.packets.len();
+            }
+
+            self.probation_sources
+                .retain(|source| !source.packets.is_empty());
+            self.stats.current.rejected_packets += rejected as u64;
+        }
+
+        self.accepted_payload_types = payload_types;
+    }
+
+    /// Drop all received RTP packets from sources not contained in `ssrcs`.
+    ///
+    /// Rejected packets are counted in [`StatsSample::rejected_packets`]. By default all sources are accepted.
+    pub fn with_accepted_ssrcs(mut self, ssrcs: impl IntoIterator<Item = u32>) -> Self {
+        self.set_accepted_ssrcs(Some(ssrcs.into_iter().collect()));
+        self
+    }
+
+    /// Set or remove the ssrc filter, see [`RtpSession::with_accepted_ssrcs`]
+    ///
+    /// Already known sources and sources in probation which are no longer accepted are removed.
+    pub fn set_accepted_ssrcs(&mut self, ssrcs: Option<Vec<u32>>) {
+        if let Some(ssrcs) = &ssrcs {
+            self.receiver
+                .retain(|receiver| ssrcs.contains(&receiver.ssrc));
+
+            let mut rejected = 0;
+
+            self.probation_sources.retain(|source| {
+                let accepted = ssrcs.contains(&source.ssrc);
+
+                if !accepted {
+                    rejected += source.packets.len();
+                }
+
+                accepted
+            });
+
+            self.stats.current.rejected_packets += rejected as u64;
+        }
+
+        self.accepted_ssrcs = ssrcs;
+    }
+
     /// Add an item to the RTCP packets source description
     pub fn with_source_description_item(
         mut self,
@@ -74,6 +400,57 @@ impl RtpSession {
         self.source_description_items.push((tag, prefix, value));
     }
 
+    /// Announce the given CNAME in the RTCP packets source description, see [`SDES_ITEM_CNAME`]
+    ///
+    /// All sessions of an endpoint should use the same CNAME, so receivers can correlate their streams.
+    /// Replaces any previously set CNAME.
+    pub fn with_cname(mut self, cname: String) -> Self {
+        self.replace_source_description_item(SDES_ITEM_CNAME, cname);
+        self
+    }
+
+    /// Announce the given MID in the RTCP packets source description, see [`SDES_ITEM_MID`]
+    ///
+    /// Replaces any previously set MID.
+    pub fn with_mid(mut self, mid: String) -> Self {
+        self.replace_source_description_item(SDES_ITEM_MID, mid);
+        self
+    }
+
+    fn replace_source_description_item(&mut self, tag: u8, value: String) {
+        self.source_description_items
+            .retain(|(item_tag, _, _)| *item_tag != tag);
+        self.add_source_description_item(tag, None, value);
+    }
+
+    /// Returns the MID a remote source announced in its RTCP source description
+    ///
+    /// This is available as soon as the SDES packet has been received, even if no RTP packets of the source have
+    /// been received yet.
+    pub fn remote_mid(&self, ssrc: u32) -> Option<&str> {
+        self.remote_source_descriptions.get(&ssrc)?.mid.as_deref()
+    }
+
+    /// Returns the CNAME a remote source announced in its RTCP source description
+    pub fn remote_cname(&self, ssrc: u32) -> Option<&str> {
+        self.remote_source_descriptions.get(&ssrc)?.cname.as_deref()
+    }
+
+    /// Returns the wall clock mapping of a remote source, once a sender report of it has been received
+    pub fn sync_info(&self, ssrc: u32) -> Option<SyncInfo> {
+        let receiver = self
+            .receiver
+            .iter()
+            .find(|receiver| receiver.ssrc == ssrc)?;
+        let last_sr = receiver.last_sr.as_ref()?;
+
+        Some(SyncInfo {
+            ntp_timestamp: last_sr.ntp_timestamp,
+            rtp_timestamp: last_sr.rtp_timestamp,
+            clock_rate: self.clock_rate,
+        })
+    }
+
     /// Sender ssrc of this session
     pub fn ssrc(&self) -> u32 {
         self.ssrc
@@ -84,9 +461,74 @@ impl RtpSession {
         self.clock_rate
     }
 
+    /// Returns the history of finished statistics samples, from oldest to newest
+    pub fn stats_history(
+        &mut self,
+        now: Instant,
+    ) -> impl ExactSizeIterator<Item = &StatsSample> + '_ {
+        self.advance_stats(now);
+        self.stats.samples()
+    }
+
+    fn ntp_timestamp(&self, now: Instant) -> NtpTimestamp {
+        let (reference_instant, reference_ntp_timestamp) = self.ntp_reference;
+
+        match now.checked_duration_since(reference_instant) {
+            Some(elapsed) => reference_ntp_timestamp + elapsed,
+            None => reference_ntp_timestamp - (reference_instant - now),
+        }
+    }
+
+    /// Local time of the given NTP timestamp, the inverse of [`RtpSession::ntp_timestamp`]
+    fn instant(&self, ntp_timestamp: NtpTimestamp) -> Option<Instant> {
+        let (reference_instant, reference_ntp_timestamp) = self.ntp_reference;
+
+        reference_instant.checked_add_signed(ntp_timestamp - reference_ntp_timestamp)
+    }
+
+    fn advance_stats(&mut self, now: Instant) {
+        let jitter = self.receiver.iter().map(|r| r.jitter).fold(0.0, f32::max);
+
+        self.stats.advance(now, jitter);
+    }
+
+    /// Change the clock rate of the RTP timestamp, e.g. after switching to a codec with a different clock rate.
+    ///
+    /// The sender's RTP timestamp at `now` is extrapolated using the old clock rate and used as anchor for the new
+    /// one, keeping RTCP sender reports continuous. Packets sent afterwards must continue from this timestamp,
+    /// see [`Packetizer::with_continuation`](crate::Packetizer::with_continuation).
+    ///
+    /// Jitter calculation of all receivers is restarted, since timestamps using different clock rates
+    /// cannot be compared.
+    pub fn set_clock_rate(&mut self, now: Instant, clock_rate: u32) {
+        if clock_rate == self.clock_rate {
+            return;
+        }
+
+        let ntp_now = self.ntp_timestamp(now);
+
+        if let Some(sender) = &mut self.sender {
+            sender.rtp_timestamp = extrapolate_timestamp(
+                sender.rtp_timestamp,
+                sender.ntp_timestamp,
+                self.clock_rate,
+                ntp_now,
+            );
+            sender.ntp_timestamp = ntp_now;
+        }
+
+        for receiver in &mut self.receiver {
+            receiver.jitter = 0.0;
+            receiver.restart_jitter = true;
+        }
+
+        self.clock_rate = clock_rate;
+    }
+
     /// Register an RTP packet before sending it out
-    pub fn send_rtp(&mut self, packet: &RtpPacket) {
+    pub fn send_rtp(&mut self, now: Instant, packet: &RtpPacket) {
         let packet = packet.get();
+        let ntp_timestamp = self.ntp_timestamp(now);
 
         let sender_status = self.sender.get_or_insert(SenderState {
             ntp_timestamp: NtpTimestamp::ZERO,
@@ -96,74 +538,267 @@ impl RtpSession {
             sender_octet_count: 0,
         });
 
-        sender_status.ntp_timestamp = NtpTimestamp::now();
+        sender_status.ntp_timestamp = ntp_timestamp;
         sender_status.rtp_timestamp =
             guess_timestamp(sender_status.rtp_timestamp, packet.timestamp());
 
         sender_status.sender_pkg_count += 1;
         sender_status.sender_octet_count += packet.payload_len() as u32;
+
+        self.advance_stats(now);
+        self.stats.current.sent_packets += 1;
+        self.stats.current.sent_bytes += packet.payload_len() as u64;
     }
 
     /// Receive an RTP packet.
     ///
     /// The session consumes the packet and puts in into a internal jitterbuffer to fix potential reordering.
-    pub fn recv_rtp(&mut self, rtp_packet: RtpPacket) {
+    pub fn recv_rtp(&mut self, now: Instant, rtp_packet: RtpPacket) {
+        self.advance_stats(now);
+
         let packet = rtp_packet.get();
 
-        let receiver_status = if let Some(receiver_status) =
-            self.receiver.iter_mut().find(|r| r.ssrc == packet.ssrc())
-        {
-            receiver_status
-        } else {
-            // Don't allow an infinite amount of receivers
-            if self.receiver.len() > 4096 {
-                return;
-            }
+        let accepted = self
+            .accepted_payload_types
+            .as_ref()
+            .is_none_or(|payload_types| payload_types.contains(&packet.payload_type()))
+            && self
+                .accepted_ssrcs
+                .as_ref()
+                .is_none_or(|ssrcs| ssrcs.contains(&packet.ssrc()));
+
+        if !accepted {
+            self.stats.current.rejected_packets += 1;
+            return;
+        }
+
+        let ssrc = packet.ssrc();
+
+        if let Some(index) = self.receiver.iter().position(|r| r.ssrc == ssrc) {
+            self.recv_rtp_from_receiver(now, index, rtp_packet);
+            return;
+        }
+
+        // Don't allow an infinite amount of receivers
+        if self.receiver.len() >= MAX_RECEIVERS {
+            return;
+        }
+
+        let Some(source) = self.probe_source(now, rtp_packet) else {
+            return;
+        };
+
+        self.receiver.push(ReceiverState {
+            ssrc,
+            jitter_buffer: JitterBuffer::default(),
+            last_rtp_received: None,
+            jitter: 0.0,
+            restart_jitter: false,
+            last_sr: source.last_sr,
+            total_lost: 0,
+        });
+
+        let index = self.receiver.len() - 1;
+
+        for (received_at, rtp_packet) in source.packets {
+            self.recv_rtp_from_receiver(received_at, index, rtp_packet);
+        }
+    }
+
+    /// Returns the new source with all its received packets once it passed the probation
+    fn probe_source(&mut self, now: Instant, rtp_packet: RtpPacket) -> Option<ProbationSource> {
+        let (ssrc, sequence_number) = {
+            let packet = rtp_packet.get();
+            (packet.ssrc(), packet.sequence_number())
+        };
 
-            self.receiver.push(ReceiverState {
-                ssrc: packet.ssrc(),
-                jitter_buffer: JitterBuffer::default(),
-                last_rtp_received: None,
-                jitter: 0.0,
+        if self.probation <= 1 {
+            return Some(ProbationSource {
+                ssrc,
+                packets: vec![(now, rtp_packet)],
                 last_sr: None,
-                total_lost: 0,
             });
+        }
 
-            self.receiver.last_mut().unwrap()
+        let index = match self
+            .probation_sources
+            .iter()
+            .position(|source| source.ssrc == ssrc)
+        {
+            Some(index) => index,
+            None => {
+                if self.probation_sources.len() >= MAX_PROBATION_SOURCES {
+                    if let Some(source) = self.probation_sources.pop_front() {
+                        self.stats.current.rejected_packets += source.packets.len() as u64;
+                    }
+                }
+
+                self.probation_sources.push_back(ProbationSource {
+                    ssrc,
+                    packets: vec![],
+                    last_sr: None,
+                });
+
+                self.probation_sources.len() - 1
+            }
         };
 
-        let now = Instant::now();
+        let source = &mut self.probation_sources[index];
+
+        let sequential = source.packets.last().is_none_or(|(_, last)| {
+            last.get().sequence_number().wrapping_add(1) == sequence_number
+        });
+
+        if !sequential {
+            // Restart the probation with this packet
+            self.stats.current.rejected_packets += source.packets.len() as u64;
+            source.packets.clear();
+        }
+
+        source.packets.push((now, rtp_packet));
+
+        if source.packets.len() < usize::from(self.probation) {
+            return None;
+        }
+
+        self.probation_sources.remove(index)
+    }
+
+    fn recv_rtp_from_receiver(&mut self, now: Instant, index: usize, rtp_packet: RtpPacket) {
+        let (payload_len, raw_timestamp) = {
+            let packet = rtp_packet.get();
+            (packet.payload_len(), packet.timestamp())
+        };
+
+        let receiver_status = &mut self.receiver[index];
+
+        self.stats.current.received_packets += 1;
+        self.stats.current.received_bytes += payload_len as u64;
+
+        if receiver_status.jitter_buffer.push(rtp_packet) {
+            // The jitter buffer restarted with the new sequence numbers, do the same for the timestamps
+            receiver_status.last_rtp_received = Some((now, u64::from(raw_timestamp)));
+            receiver_status.restart_jitter = true;
+
+            self.stats.current.stream_resets += 1;
+
+            if self.stream_resets.len() >= MAX_RECEIVERS {
+                self.stream_resets.pop_front();
+            }
+
+            self.stream_resets.push_back(receiver_status.ssrc);
+            return;
+        }
+
+        if receiver_status.jitter_buffer.has_probable_reset() {
+            // Possibly a stray packet, its timestamp must not be used until the restart is confirmed
+            return;
+        }
 
         // Update jitter and find extended timestamp
         let timestamp = if let Some((last_rtp_instant, last_rtp_timestamp)) =
             receiver_status.last_rtp_received
         {
+            let timestamp = guess_timestamp(last_rtp_timestamp, raw_timestamp);
+
             // Rj - Ri
             let a = now - last_rtp_instant;
             let a = (a.as_secs_f32() * self.clock_rate as f32) as i64;
 
             // Sj - Si
-            let b = packet.timestamp() as i64 - last_rtp_timestamp as i64;
+            let b = timestamp as i64 - last_rtp_timestamp as i64;
 
             // (Rj - Ri) - (Sj - Si)
             let d = a.abs_diff(b);
 
-            receiver_status.jitter =
-                receiver_status.jitter + ((d as f32).abs() - receiver_status.jitter) / 16.;
+            // Timestamps jumped (e.g. after a hold), don't let this distort the jitter
+            let discontinuous =
+                d as f64 > MAX_TIMESTAMP_DISCONTINUITY.as_secs_f64() * f64::from(self.clock_rate);
 
-            guess_timestamp(last_rtp_timestamp, packet.timestamp())
+            if !discontinuous {
+                if receiver_status.restart_jitter {
+                    receiver_status.restart_jitter = false;
+                } else {
+                    receiver_status.jitter =
+                        receiver_status.jitter + ((d as f32).abs() - receiver_status.jitter) / 16.;
+                }
+            }
+
+            timestamp
         } else {
-            packet.timestamp() as u64
+            u64::from(raw_timestamp)
         };
 
         receiver_status.last_rtp_received = Some((now, timestamp));
+    }
+
+    /// Returns the ssrc of a remote source which restarted its stream.
+    ///
+    /// A restart is detected when the sequence numbers jump and the following packet confirms the new sequence
+    /// (RFC 3550 A.1), a single stray packet is ignored. Packets of the source buffered before the restart are
+    /// discarded, decoders should flush their state.
+    pub fn pop_stream_reset(&mut self) -> Option<u32> {
+        self.stream_resets.pop_front()
+    }
+
+    /// Remove the state of the remote source with the given ssrc, discarding all of its buffered packets.
+    ///
+    /// Returns if the source was known to the session.
+    pub fn remove_receiver(&mut self, ssrc: u32) -> bool {
+        self.remote_source_descriptions.remove(&ssrc);
+        self.probation_sources.retain(|source| source.ssrc != ssrc);
+
+        let len = self.receiver.len();
+        self.receiver.retain(|receiver| receiver.ssrc != ssrc);
+        len != self.receiver.len()
+    }
 
-        receiver_status.jitter_buffer.push(rtp_packet);
+    /// Returns the ssrc of a remote source that was removed because of the receiver timeout
+    pub fn pop_timed_out_receiver(&mut self) -> Option<u32> {
+        self.timed_out_receivers.pop_front()
     }
 
-    pub fn pop_rtp(&mut self, jitter_buffer_length: Option<Duration>) -> Option<RtpPacket> {
+    fn remove_timed_out_receivers(&mut self, now: Instant) {
+        let Some(timeout) = self.receiver_timeout else {
+            return;
+        };
+
+        let timed_out_receivers = &mut self.timed_out_receivers;
+        let remote_source_descriptions = &mut self.remote_source_descriptions;
+
+        self.receiver.retain(|receiver| {
+            let active = receiver
+                .last_rtp_received
+                .is_some_and(|(instant, _)| now.saturating_duration_since(instant) < timeout);
+
+            if !active {
+                remote_source_descriptions.remove(&receiver.ssrc);
+
+                if timed_out_receivers.len() >= MAX_RECEIVERS {
+                    timed_out_receivers.pop_front();
+                }
+
+                timed_out_receivers.push_back(receiver.ssrc);
+            }
+
+            active
+        });
+    }
+
+    /// Returns the next RTP packet which has been in the jitter buffer for at least `jitter_buffer_length`.
+    ///
+    /// Uses the session's default length if `None`, see [`RtpSession::with_jitter_buffer_length`].
+    pub fn pop_rtp(
+        &mut self,
+        now: Instant,
+        jitter_buffer_length: Option<Duration>,
+    ) -> Option<RtpPacket> {
+        self.advance_stats(now);
+        self.remove_timed_out_receivers(now);
+
+        // A length beyond the representable time range means no packet has been buffered long enough yet
         let pop_earliest =
-            Instant::now() - jitter_buffer_length.unwrap_or(DEFAULT_JITTERBUFFER_LENGTH);
+            now.checked_sub(jitter_buffer_length.unwrap_or(self.jitter_buffer_length))?;
 
         for receiver in &mut self.receiver {
             let Some((last_rtp_received_instant, last_rtp_received_timestamp)) =
@@ -179,32 +814,209 @@ impl RtpSession {
                 pop_earliest,
             );
 
-            if let Some(packet) = receiver.jitter_buffer.pop(max_timestamp) {
-                return Some(packet);
+            let lost_before = receiver.jitter_buffer.lost;
+            let packet = receiver.jitter_buffer.pop(max_timestamp);
+            self.stats.current.lost_packets += receiver.jitter_buffer.lost - lost_before;
+
+            if packet.is_some() {
+                return packet;
             }
         }
 
         None
     }
 
-    pub fn recv_rtcp(&mut self, packet: rtcp_types::Packet<'_>) {
-        // TODO: read reports
-        if let rtcp_types::Packet::Sr(sr) = packet {
-            if let Some(receiver) = self
-                .receiver
-                .iter_mut()
-                .find(|status| status.ssrc == sr.ssrc())
-            {
-                receiver.last_sr = Some(NtpTimestamp::now());
+    /// Like [`RtpSession::pop_rtp`], but returns the packet as frame to pass it on to a
+    /// [`DePacketizer`](crate::DePacketizer).
+    ///
+    /// Once a sender report of the packet's source has been received, the frame's
+    /// [capture time](Frame::capture_time) is set to the sender's wall clock time of the packet (see [`SyncInfo`]),
+    /// mapped to local time assuming the wall clocks of both endpoints are synchronized. Frames of streams sharing a
+    /// remote CNAME can be played out in sync by comparing their capture times.
+    pub fn pop_rtp_frame(
+        &mut self,
+        now: Instant,
+        jitter_buffer_length: Option<Duration>,
+    ) -> Option<Frame<Rtp>> {
+        let packet = self.pop_rtp(now, jitter_buffer_length)?;
+
+        let (ssrc, timestamp) = {
+            let packet = packet.get();
+            (packet.ssrc(), packet.timestamp())
+        };
+
+        let capture_time = self
+            .sync_info(ssrc)
+            .and_then(|sync_info| self.instant(sync_info.to_ntp_timestamp(timestamp)));
+
+        Some(Frame::new(packet, u64::from(timestamp)).with_capture_time(capture_time))
+    }
+
+    pub fn recv_rtcp(&mut self, now: Instant, packet: rtcp_types::Packet<'_>) {
+        let now = self.ntp_timestamp(now);
+
+        match packet {
+            rtcp_types::Packet::Sr(sr) => {
+                let last_sr = ReceivedSenderReport {
+                    received_at: now,
+                    ntp_timestamp: NtpTimestamp::from_fixed_u64(sr.ntp_timestamp()),
+                    rtp_timestamp: sr.rtp_timestamp(),
+                };
+
+                if let Some(receiver) = self
+                    .receiver
+                    .iter_mut()
+                    .find(|status| status.ssrc == sr.ssrc())
+                {
+                    receiver.last_sr = Some(last_sr);
+                } else if let Some(source) = self
+                    .probation_sources
+                    .iter_mut()
+                    .find(|source| source.ssrc == sr.ssrc())
+                {
+                    source.last_sr = Some(last_sr);
+                }
+
+                for report_block in sr.report_blocks() {
+                    self.recv_report_block(now, sr.ssrc(), report_block);
+                }
+            }
+            rtcp_types::Packet::Rr(rr) => {
+                for report_block in rr.report_blocks() {
+                    self.recv_report_block(now, rr.ssrc(), report_block);
+                }
             }
+            rtcp_types::Packet::Sdes(sdes) => {
+                for chunk in sdes.chunks() {
+                    let mut cname = None;
+                    let mut mid = None;
+
+                    for item in chunk.items() {
+                        let Ok(value) = std::str::from_utf8(item.value()) else {
+                            continue;
+                        };
+
+                        match item.type_() {
+                            SDES_ITEM_CNAME => cname = Some(value.to_owned()),
+                            SDES_ITEM_MID => mid = Some(value.to_owned()),
+                            _ => {}
+                        }
+                    }
+
+                    if cname.is_none() && mid.is_none() {
+                        continue;
+                    }
+
+                    if self.remote_source_descriptions.len() >= MAX_RECEIVERS
+                        && !self.remote_source_descriptions.contains_key(&chunk.ssrc())
+                    {
+                        continue;
+                    }
+
+                    let description = self
+                        .remote_source_descriptions
+                        .entry(chunk.ssrc())
+                        .or_default();
+
+                    if cname.is_some() {
+                        description.cname = cname;
+                    }
+
+                    if mid.is_some() {
+                        description.mid = mid;
+                    }
+                }
+            }
+            _ => {}
         }
     }
 
+    fn recv_report_block(&mut self, now: NtpTimestamp, reporter_ssrc: u32, block: ReportBlock<'_>) {
+        // Only interested in reports about our own stream
+        if block.ssrc() != self.ssrc {
+            return;
+        }
+
+        let last_sr = block.last_sender_report_timestamp();
+
+        let rtt = if last_sr == 0 {
+            None
+        } else {
+            // RTT = A - LSR - DLSR, all in 1/65536 seconds
+            let rtt = now
+                .to_fixed_u32()
+                .wrapping_sub(last_sr)
+                .wrapping_sub(block.delay_since_last_sender_report_timestamp());
+
+            Some(Duration::from_secs_f64(f64::from(rtt) / 65536.0))
+        };
+
+        let report = RemoteReport {
+            reporter_ssrc,
+            fraction_lost: f32::from(block.fraction_lost()) / 256.0,
+            cumulative_lost: block.cumulative_lost(),
+            jitter: block.interarrival_jitter(),
+            rtt,
+        };
+
+        if self.remote_reports.len() >= MAX_PENDING_REMOTE_REPORTS {
+            self.remote_reports.pop_front();
+        }
+
+        self.remote_reports.push_back(report);
+        self.remote_reports_tx.send_replace(Some(report));
+    }
+
+    /// Create a receiver for reports about the local stream, to forward them to the sending pipeline.
+    ///
+    /// Unlike [`RtpSession::pop_remote_report`] the receiver only provides the latest report. Reports are
+    /// available to both independently.
+    pub fn subscribe_remote_reports(&self) -> RemoteReportReceiver {
+        RemoteReportReceiver {
+            rx: self.remote_reports_tx.subscribe(),
+        }
+    }
+
+    /// Returns the oldest unread report about the local stream received from a remote receiver
+    ///
+    /// Only the most recent reports are kept, older ones are discarded if they aren't read in time.
+    pub fn pop_remote_report(&mut self) -> Option<RemoteReport> {
+        self.remote_reports.pop_front()
+    }
+
     /// Generate RTCP sender or receiver report packet.
     ///
     /// This resets the internal received & lost packets counter for every receiver.
-    pub fn write_rtcp_report(&mut self, dst: &mut [u8]) -> Result<usize, RtcpWriteError> {
-        let now = NtpTimestamp::now();
+    pub fn write_rtcp_report(
+        &mut self,
+        now: Instant,
+        dst: &mut [u8],
+    ) -> Result<usize, RtcpWriteError> {
+        self.build_rtcp_report(now).write_into(dst)
+    }
+
+    /// Generate a final RTCP report followed by a BYE packet, to be sent when the session is shut down.
+    ///
+    /// The session should not be used to send any more RTP packets afterwards.
+    pub fn write_rtcp_bye(
+        &mut self,
+        now: Instant,
+        reason: Option<&str>,
+        dst: &mut [u8],
+    ) -> Result<usize, RtcpWriteError> {
+        let mut bye = Bye::builder().add_source(self.ssrc);
+
+        if let Some(reason) = reason {
+            bye = bye.reason(reason);
+        }
+
+        self.build_rtcp_report(now).add_packet(bye).write_into(dst)
+    }
+
+    fn build_rtcp_report(&mut self, now: Instant) -> CompoundBuilder<'_> {
+        self.remove_timed_out_receivers(now);
+
+        let now = self.ntp_timestamp(now);
 
         let mut report_blocks = vec![];
 
@@ -219,11 +1031,12 @@ impl RtpSession {
             let fraction_lost = (lost as f64 / (received + lost) as f64) * 255.0;
             let fraction_lost = fraction_lost as u32;
 
-            let (last_sr, delay) = if let Some(last_sr) = receiver.last_sr {
-                let delay = now - last_sr;
+            let (last_sr, delay) = if let Some(last_sr) = &receiver.last_sr {
+                let delay = now - last_sr.received_at;
                 let delay = (delay.as_seconds_f64() * 65536.0) as u32;
 
-                let last_sr = last_sr.to_fixed_u32();
+                // LSR must be the remote's NTP timestamp, so it can calculate the RTT with its own clock
+                let last_sr = last_sr.ntp_timestamp.to_fixed_u32();
 
                 (last_sr, delay)
             } else {
@@ -250,11 +1063,12 @@ impl RtpSession {
 
         // Add report block
         if let Some(sender_info) = &self.sender {
-            let rtp_timestamp = {
-                let offset = (self.clock_rate * (now - sender_info.ntp_timestamp)).as_seconds_f64()
-                    * self.clock_rate as f64;
-                sender_info.rtp_timestamp + offset as u64
-            };
+            let rtp_timestamp = extrapolate_timestamp(
+                sender_info.rtp_timestamp,
+                sender_info.ntp_timestamp,
+                self.clock_rate,
+                now,
+            );
 
             let mut sr = SenderReport::builder(self.ssrc)
                 .ntp_timestamp(now.to_fixed_u64())
@@ -294,8 +1108,7 @@ impl RtpSession {
             compound = compound.add_packet(SdesBuilder::default().add_chunk(chunk));
         };
 
-        // write into dst
-        compound.write_into(dst)
+        compound
     }
 }
 
@@ -310,6 +1123,619 @@ fn map_instant_to_rtp_timestamp(
     (reference_timestamp as i64 + delta_in_rtp_timesteps) as u64
 }
 
+/// Advance an RTP timestamp taken at `reference` to `now`
+fn extrapolate_timestamp(
+    timestamp: u64,
+    reference: NtpTimestamp,
+    clock_rate: u32,
+    now: NtpTimestamp,
+) -> u64 {
+    let offset = (now - reference).as_seconds_f64() * f64::from(clock_rate);
+    timestamp.saturating_add_signed(offset as i64)
+}
+
 fn lower_32bits(i: u64) -> u32 {
     (i & u64::from(u32::MAX)) as u32
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rtcp_types::{Compound, Packet};
+    use rtp_types::RtpPacketBuilder;
+
+    const SSRC: u32 = 1;
+    const REMOTE_SSRC: u32 = 2;
+
+    fn ntp_start() -> NtpTimestamp {
+        NtpTimestamp::from_fixed_u64(3_900_000_000 << 32)
+    }
+
+    fn make_packet(ssrc: u32, sequence_number: u16, timestamp: u32) -> RtpPacket {
+        RtpPacket::new(
+            &RtpPacketBuilder::new()
+                .ssrc(ssrc)
+                .sequence_number(sequence_number)
+                .timestamp(timestamp)
+                .payload(&[0u8; 160][..]),
+        )
+    }
+
+    fn write_report(session: &mut RtpSession, now: Instant) -> Vec<u8> {
+        let mut buf = vec![0u8; 1500];
+        let len = session.write_rtcp_report(now, &mut buf).unwrap();
+        buf.truncate(len);
+        buf
+    }
+
+    fn recv_report(session: &mut RtpSession, now: Instant, report: &[u8]) {
+        for packet in Compound::parse(report).unwrap() {
+            session.recv_rtcp(now, packet.unwrap());
+        }
+    }
+
+    #[test]
+    fn sender_report() {
+        let start = Instant::now();
+        let mut session = RtpSession::new(start, ntp_start(), SSRC, 8000);
+
+        session.send_rtp(start, &make_packet(SSRC, 1, 1000));
+
+        let now = start + Duration::from_millis(500);
+        let report = write_report(&mut session, now);
+
+        let mut compound = Compound::parse(&report).unwrap();
+        let Some(Ok(Packet::Sr(sr))) = compound.next() else {
+            panic!("expected sender report");
+        };
+
+        assert_eq!(sr.ssrc(), SSRC);
+        assert_eq!(
+            sr.ntp_timestamp(),
+            (ntp_start() + Duration::from_millis(500)).to_fixed_u64()
+        );
+        assert_eq!(sr.rtp_timestamp(), 1000 + 4000);
+        assert_eq!(sr.packet_count(), 1);
+        assert_eq!(sr.octet_count(), 160);
+        assert_eq!(sr.report_blocks().count(), 0);
+    }
+
+    #[test]
+    fn receiver_report() {
+        let start = Instant::now();
+        let mut session = RtpSession::new(start, ntp_start(), SSRC, 8000);
+
+        session.recv_rtp(start, make_packet(REMOTE_SSRC, 10, 0));
+        session.recv_rtp(start, make_packet(REMOTE_SSRC, 12, 320));
+
+        let now = start + Duration::from_millis(200);
+        assert!(session.pop_rtp(now, None).is_some());
+        assert!(session.pop_rtp(now, None).is_some());
+        assert!(session.pop_rtp(now, None).is_none());
+
+        let report = write_report(&mut session, now);
+
+        let mut compound = Compound::parse(&report).unwrap();
+        let Some(Ok(Packet::Rr(rr))) = compound.next() else {
+            panic!("expected receiver report");
+        };
+
+        assert_eq!(rr.ssrc(), SSRC);
+
+        let report_block = rr.report_blocks().next().unwrap();
+        assert_eq!(report_block.ssrc(), REMOTE_SSRC);
+        assert_eq!(report_block.extended_sequence_number(), 12);
+        assert_eq!(report_block.fraction_lost(), 255 / 3);
+        assert_eq!(report_block.cumulative_lost(), 1);
+        assert_eq!(report_block.last_sender_report_timestamp(), 0);
+        assert_eq!(report_block.delay_since_last_sender_report_timestamp(), 0);
+    }
+
+    #[test]
+    fn receiver_timeout() {
+        let start = Instant::now();
+        let mut session = RtpSession::new(start, ntp_start(), SSRC, 8000)
+            .with_receiver_timeout(Duration::from_secs(5));
+
+        session.recv_rtp(start, make_packet(REMOTE_SSRC, 1, 0));
+        session.recv_rtp(start + Duration::from_secs(3), make_packet(3, 1, 0));
+
+        assert!(session
+            .pop_rtp(start + Duration::from_secs(4), None)
+            .is_some());
+        assert!(session.pop_timed_out_receiver().is_none());
+
+        session.pop_rtp(start + Duration::from_secs(6), None);
+        assert_eq!(session.pop_timed_out_receiver(), Some(REMOTE_SSRC));
+        assert!(session.pop_timed_out_receiver().is_none());
+
+        // The source is recreated when it starts sending again
+        session.recv_rtp(
+            start + Duration::from_secs(7),
+            make_packet(REMOTE_SSRC, 2, 160),
+        );
+        assert_eq!(session.receiver.len(), 2);
+    }
+
+    #[test]
+    fn remove_receiver() {
+        let start = Instant::now();
+        let mut session = RtpSession::new(start, ntp_start(), SSRC, 8000);
+
+        session.recv_rtp(start, make_packet(REMOTE_SSRC, 1, 0));
+
+        assert!(session.remove_receiver(REMOTE_SSRC));
+        assert!(!session.remove_receiver(REMOTE_SSRC));
+
+        // Buffered packets are discarded with the source
+        assert!(session
+            .pop_rtp(start + Duration::from_secs(1), None)
+            .is_none());
+        assert!(session.pop_timed_out_receiver().is_none());
+    }
+
+    #[test]
+    fn mid_roundtrip() {
+        let start = Instant::now();
+        let mut local =
+            RtpSession::new(start, ntp_start(), SSRC, 8000).with_mid("audio".to_owned());
+        let mut remote = RtpSession::new(start, ntp_start(), REMOTE_SSRC, 8000);
+
+        let report = write_report(&mut local, start);
+
+        let sdes = Compound::parse(&report)
+            .unwrap()
+            .find_map(|packet| match packet.unwrap() {
+                Packet::Sdes(sdes) => Some(sdes),
+                _ => None,
+            })
+            .unwrap();
+        let chunk = sdes.chunks().next().unwrap();
+        let item = chunk.items().next().unwrap();
+        assert_eq!(chunk.ssrc(), SSRC);
+        assert_eq!(item.type_(), SDES_ITEM_MID);
+        assert_eq!(item.value(), b"audio");
+
+        assert_eq!(remote.remote_mid(SSRC), None);
+        recv_report(&mut remote, start, &report);
+        assert_eq!(remote.remote_mid(SSRC), Some("audio"));
+        assert_eq!(remote.remote_cname(SSRC), None);
+    }
+
+    #[test]
+    fn payload_type_filter() {
+        let start = Instant::now();
+        let mut session =
+            RtpSession::new(start, ntp_start(), SSRC, 8000).with_accepted_payload_types([8]);
+
+        let make_packet = |sequence_number, payload_type| {
+            RtpPacket::new(
+                &RtpPacketBuilder::new()
+                    .ssrc(REMOTE_SSRC)
+                    .sequence_number(sequence_number)
+                    .payload_type(payload_type)
+                    .payload(&[0u8; 160][..]),
+            )
+        };
+
+        session.recv_rtp(start, make_packet(1, 0));
+        session.recv_rtp(start, make_packet(2, 8));
+
+        assert_eq!(session.stats.current.rejected_packets, 1);
+        assert_eq!(session.stats.current.received_packets, 1);
+
+        let packet = session
+            .pop_rtp(start + Duration::from_secs(1), None)
+            .unwrap();
+        assert_eq!(packet.get().payload_type(), 8);
+        assert!(session
+            .pop_rtp(start + Duration::from_secs(1), None)
+            .is_none());
+    }
+
+    #[test]
+    fn ssrc_filter() {
+        let start = Instant::now();
+        let mut session = RtpSession::new(start, ntp_start(), SSRC, 8000);
+
+        session.recv_rtp(start, make_packet(REMOTE_SSRC, 1, 0));
+        session.recv_rtp(start, make_packet(3, 1, 0));
+        assert_eq!(session.receiver.len(), 2);
+
+        // Sources no longer accepted are removed
+        session.set_accepted_ssrcs(Some(vec![3]));
+        assert_eq!(session.receiver.len(), 1);
+
+        session.recv_rtp(start, make_packet(REMOTE_SSRC, 2, 160));
+        session.recv_rtp(start, make_packet(3, 2, 160));
+        assert_eq!(session.stats.current.rejected_packets, 1);
+
+        let now = start + Duration::from_secs(1);
+        assert_eq!(session.pop_rtp(now, None).unwrap().get().ssrc(), 3);
+        assert_eq!(session.pop_rtp(now, None).unwrap().get().ssrc(), 3);
+        assert!(session.pop_rtp(now, None).is_none());
+
+        // Removing the filter accepts all sources again
+        session.set_accepted_ssrcs(None);
+        session.recv_rtp(now, make_packet(REMOTE_SSRC, 3, 320));
+        assert_eq!(session.receiver.len(), 2);
+    }
+
+    #[test]
+    fn cname_replaces_previous() {
+        let start = Instant::now();
+        let mut session = RtpSession::new(start, ntp_start(), SSRC, 8000)
+            .with_source_description_item(SDES_ITEM_CNAME, None, "default".to_owned())
+            .with_cname("user".to_owned());
+
+        let report = write_report(&mut session, start);
+
+        let sdes = Compound::parse(&report)
+            .unwrap()
+            .find_map(|packet| match packet.unwrap() {
+                Packet::Sdes(sdes) => Some(sdes),
+                _ => None,
+            })
+            .unwrap();
+        let chunk = sdes.chunks().next().unwrap();
+        let cnames: Vec<&[u8]> = chunk
+            .items()
+            .filter(|item| item.type_() == SDES_ITEM_CNAME)
+            .map(|item| item.value())
+            .collect();
+
+        assert_eq!(cnames, [b"user"]);
+    }
+
+    #[test]
+    fn jitter_buffer_frames() {
+        let start = Instant::now();
+
+        let session = RtpSession::new(start, ntp_start(), SSRC, 48000)
+            .with_jitter_buffer_frames(3, Duration::from_millis(60));
+        assert_eq!(session.jitter_buffer_length(), Duration::from_millis(180));
+        assert_eq!(session.jitter_buffer_frames(), Some(3));
+
+        let mut session = RtpSession::new(start, ntp_start(), SSRC, 48000)
+            .with_jitter_buffer_frames(u32::MAX, Duration::MAX);
+        assert_eq!(session.jitter_buffer_length(), Duration::MAX);
+        assert_eq!(session.jitter_buffer_frames(), Some(1));
+
+        session.recv_rtp(start, make_packet(REMOTE_SSRC, 1, 0));
+        assert!(session.pop_rtp(start, None).is_none());
+    }
+
+    #[test]
+    fn remote_report_rtt() {
+        let start = Instant::now();
+        let mut session = RtpSession::new(start, ntp_start(), SSRC, 8000);
+        let mut remote_reports = session.subscribe_remote_reports();
+
+        // Remote received our sender report at `start` and sent its report 500ms later
+        let rr = ReceiverReport::builder(REMOTE_SSRC).add_report_block(
+            ReportBlock::builder(SSRC)
+                .fraction_lost(64)
+                .cumulative_lost(10)
+                .interarrival_jitter(80)
+                .last_sender_report_timestamp(ntp_start().to_fixed_u32())
+                .delay_since_last_sender_report_timestamp(65536 / 2),
+        );
+
+        let mut buf = vec![0u8; 1500];
+        let len = CompoundBuilder::default()
+            .add_packet(rr)
+            .write_into(&mut buf)
+            .unwrap();
+
+        assert!(remote_reports.latest().is_none());
+
+        recv_report(
+            &mut session,
+            start + Duration::from_millis(700),
+            &buf[..len],
+        );
+
+        let report = session.pop_remote_report().unwrap();
+        assert_eq!(report.reporter_ssrc, REMOTE_SSRC);
+        assert_eq!(report.fraction_lost, 0.25);
+        assert_eq!(report.cumulative_lost, 10);
+        assert_eq!(report.jitter, 80);
+
+        let rtt = report.rtt.unwrap();
+        assert!(rtt.abs_diff(Duration::from_millis(200)) < Duration::from_millis(1));
+
+        assert!(session.pop_remote_report().is_none());
+        assert_eq!(remote_reports.latest(), Some(report));
+        assert_eq!(remote_reports.latest(), None);
+    }
+
+    #[test]
+    fn clock_rate_change_keeps_sender_reports_continuous() {
+        let start = Instant::now();
+        let mut session = RtpSession::new(start, ntp_start(), SSRC, 8000);
+
+        let sr_rtp_timestamp = |session: &mut RtpSession, now| {
+            let report = write_report(session, now);
+            let Some(Ok(Packet::Sr(sr))) = Compound::parse(&report).unwrap().next() else {
+                panic!("expected sender report");
+            };
+            sr.rtp_timestamp()
+        };
+
+        session.send_rtp(start, &make_packet(SSRC, 1, 0));
+        session.send_rtp(start + Duration::from_secs(1), &make_packet(SSRC, 2, 8000));
+        assert_eq!(
+            sr_rtp_timestamp(&mut session, start + Duration::from_millis(1500)),
+            12000
+        );
+
+        // Switch to a 16kHz codec 500ms after the last packet
+        session.set_clock_rate(start + Duration::from_millis(1500), 16000);
+        assert_eq!(
+            sr_rtp_timestamp(&mut session, start + Duration::from_millis(1500)),
+            12000
+        );
+        assert_eq!(
+            sr_rtp_timestamp(&mut session, start + Duration::from_secs(2)),
+            20000
+        );
+
+        // Packets continue from the anchor using the new clock rate
+        session.send_rtp(start + Duration::from_secs(3), &make_packet(SSRC, 3, 36000));
+        assert_eq!(
+            sr_rtp_timestamp(&mut session, start + Duration::from_millis(3500)),
+            44000
+        );
+    }
+
+    /// Remote sends a packet at `start` and its sender report 100ms later, which is received by `local` immediately
+    fn exchange_sender_report(local: &mut RtpSession, start: Instant) {
+        let mut remote = RtpSession::new(start, ntp_start(), REMOTE_SSRC, 8000);
+
+        let packet = make_packet(REMOTE_SSRC, 1, 1000);
+        remote.send_rtp(start, &packet);
+        local.recv_rtp(start, packet);
+
+        let now = start + Duration::from_millis(100);
+        let report = write_report(&mut remote, now);
+        recv_report(local, now, &report);
+    }
+
+    #[test]
+    fn last_sender_report_in_receiver_report() {
+        let start = Instant::now();
+        let mut session = RtpSession::new(start, ntp_start(), SSRC, 8000);
+
+        exchange_sender_report(&mut session, start);
+
+        let report = write_report(&mut session, start + Duration::from_millis(600));
+
+        let mut compound = Compound::parse(&report).unwrap();
+        let Some(Ok(Packet::Rr(rr))) = compound.next() else {
+            panic!("expected receiver report");
+        };
+
+        let report_block = rr.report_blocks().next().unwrap();
+        assert_eq!(report_block.ssrc(), REMOTE_SSRC);
+        assert_eq!(
+            report_block.last_sender_report_timestamp(),
+            (ntp_start() + Duration::from_millis(100)).to_fixed_u32()
+        );
+        assert!(
+            report_block
+                .delay_since_last_sender_report_timestamp()
+                .abs_diff(65536 / 2)
+                <= 1
+        );
+    }
+
+    #[test]
+    fn sync_info() {
+        let start = Instant::now();
+        let mut session = RtpSession::new(start, ntp_start(), SSRC, 8000);
+
+        assert!(session.sync_info(REMOTE_SSRC).is_none());
+
+        exchange_sender_report(&mut session, start);
+
+        let sync_info = session.sync_info(REMOTE_SSRC).unwrap();
+        assert_eq!(sync_info.rtp_timestamp, 1000 + 800);
+        assert_eq!(sync_info.clock_rate, 8000);
+
+        let sr_time = ntp_start() + Duration::from_millis(100);
+        assert!((sync_info.ntp_timestamp - sr_time).abs() < Duration::from_micros(1));
+
+        let later = sync_info.to_ntp_timestamp(1800 + 8000);
+        assert!((later - (sr_time + Duration::from_secs(1))).abs() < Duration::from_micros(1));
+
+        let earlier = sync_info.to_ntp_timestamp(1000);
+        assert!((earlier - ntp_start()).abs() < Duration::from_micros(1));
+    }
+
+    #[test]
+    fn synchronized_capture_time() {
+        let start = Instant::now();
+        let mut session = RtpSession::new(start, ntp_start(), SSRC, 8000);
+
+        session.recv_rtp(start, make_packet(3, 1, 0));
+        exchange_sender_report(&mut session, start);
+
+        let now = start + Duration::from_secs(1);
+        let mut frames = vec![];
+
+        while let Some(frame) = session.pop_rtp_frame(now, None) {
+            frames.push(frame);
+        }
+
+        let remote_frame = frames
+            .iter()
+            .find(|frame| frame.data().get().ssrc() == REMOTE_SSRC)
+            .unwrap();
+        assert_eq!(remote_frame.timestamp, 1000);
+
+        // The packet was sent at `start`, according to the sender report
+        let capture_time = remote_frame.capture_time.unwrap();
+        assert!(capture_time.signed_duration_since(start).abs() < Duration::from_micros(1));
+
+        // No sender report of the other source
+        let other_frame = frames
+            .iter()
+            .find(|frame| frame.data().get().ssrc() == 3)
+            .unwrap();
+        assert!(other_frame.capture_time.is_none());
+    }
+
+    #[test]
+    fn probation_accepts_sequential_packets() {
+        let start = Instant::now();
+        let mut session = RtpSession::new(start, ntp_start(), SSRC, 8000).with_probation(2);
+
+        session.recv_rtp(start, make_packet(REMOTE_SSRC, 1, 0));
+        assert!(session.receiver.is_empty());
+
+        session.recv_rtp(start, make_packet(REMOTE_SSRC, 2, 160));
+        assert_eq!(session.receiver.len(), 1);
+        assert!(session.probation_sources.is_empty());
+
+        // Packets received during the probation are passed on
+        let now = start + Duration::from_secs(1);
+        assert_eq!(
+            session.pop_rtp(now, None).unwrap().get().sequence_number(),
+            1
+        );
+        assert_eq!(
+            session.pop_rtp(now, None).unwrap().get().sequence_number(),
+            2
+        );
+        assert!(session.pop_rtp(now, None).is_none());
+        assert_eq!(session.stats.current.rejected_packets, 0);
+    }
+
+    #[test]
+    fn probation_restarts_on_gap() {
+        let start = Instant::now();
+        let mut session = RtpSession::new(start, ntp_start(), SSRC, 8000).with_probation(3);
+
+        session.recv_rtp(start, make_packet(REMOTE_SSRC, 1, 0));
+        session.recv_rtp(start, make_packet(REMOTE_SSRC, 2, 160));
+        session.recv_rtp(start, make_packet(REMOTE_SSRC, 5, 640));
+        assert!(session.receiver.is_empty());
+        assert_eq!(session.stats.current.rejected_packets, 2);
+
+        session.recv_rtp(start, make_packet(REMOTE_SSRC, 6, 800));
+        assert!(session.receiver.is_empty());
+
+        session.recv_rtp(start, make_packet(REMOTE_SSRC, 7, 960));
+        assert_eq!(session.receiver.len(), 1);
+
+        let now = start + Duration::from_secs(1);
+        let sequence_numbers: Vec<u16> = std::iter::from_fn(|| session.pop_rtp(now, None))
+            .map(|packet| packet.get().sequence_number())
+            .collect();
+        assert_eq!(sequence_numbers, [5, 6, 7]);
+    }
+
+    #[test]
+    fn probation_keeps_sender_report() {
+        let start = Instant::now();
+        let mut session = RtpSession::new(start, ntp_start(), SSRC, 8000).with_probation(2);
+
+        exchange_sender_report(&mut session, start);
+        assert!(session.receiver.is_empty());
+
+        session.recv_rtp(
+            start + Duration::from_millis(200),
+            make_packet(REMOTE_SSRC, 2, 1160),
+        );
+
+        let sync_info = session.sync_info(REMOTE_SSRC).unwrap();
+        assert_eq!(sync_info.rtp_timestamp, 1800);
+    }
+
+    #[test]
+    fn probation_purged_by_filters() {
+        let start = Instant::now();
+        let mut session = RtpSession::new(start, ntp_start(), SSRC, 8000).with_probation(2);
+
+        session.recv_rtp(start, make_packet(REMOTE_SSRC, 1, 0));
+        session.recv_rtp(start, make_packet(3, 1, 0));
+
+        session.set_accepted_ssrcs(Some(vec![3]));
+        assert_eq!(session.probation_sources.len(), 1);
+        assert_eq!(session.stats.current.rejected_packets, 1);
+
+        session.set_accepted_payload_types(Some(vec![8]));
+        assert!(session.probation_sources.is_empty());
+        assert_eq!(session.stats.current.rejected_packets, 2);
+
+        // The probation starts over once the sources are accepted again
+        session.set_accepted_ssrcs(None);
+        session.set_accepted_payload_types(None);
+        session.recv_rtp(start, make_packet(REMOTE_SSRC, 2, 160));
+        assert!(session.receiver.is_empty());
+    }
+
+    #[test]
+    fn stray_packet_is_no_stream_reset() {
+        let start = Instant::now();
+        let mut session = RtpSession::new(start, ntp_start(), SSRC, 8000);
+
+        session.recv_rtp(start, make_packet(REMOTE_SSRC, 100, 0));
+        session.recv_rtp(start, make_packet(REMOTE_SSRC, 101, 160));
+        session.recv_rtp(start, make_packet(REMOTE_SSRC, 30000, 1_000_000));
+        session.recv_rtp(start, make_packet(REMOTE_SSRC, 102, 320));
+        session.recv_rtp(start, make_packet(REMOTE_SSRC, 103, 480));
+
+        assert!(session.pop_stream_reset().is_none());
+        assert_eq!(session.stats.current.stream_resets, 0);
+        assert_eq!(session.receiver[0].last_rtp_received, Some((start, 480)));
+
+        let now = start + Duration::from_secs(1);
+        let sequence_numbers: Vec<u16> = std::iter::from_fn(|| session.pop_rtp(now, None))
+            .map(|packet| packet.get().sequence_number())
+            .collect();
+        assert_eq!(sequence_numbers, [100, 101, 102, 103]);
+    }
+
+    #[test]
+    fn stream_restart_is_reported_once() {
+        let start = Instant::now();
+        let mut session = RtpSession::new(start, ntp_start(), SSRC, 8000);
+
+        session.recv_rtp(start, make_packet(REMOTE_SSRC, 20000, 0));
+        session.recv_rtp(start, make_packet(REMOTE_SSRC, 20001, 160));
+
+        // Restart with new sequence numbers and timestamps
+        session.recv_rtp(start, make_packet(REMOTE_SSRC, 5, 50000));
+        assert!(session.pop_stream_reset().is_none());
+
+        session.recv_rtp(start, make_packet(REMOTE_SSRC, 6, 50160));
+        session.recv_rtp(start, make_packet(REMOTE_SSRC, 7, 50320));
+
+        assert_eq!(session.pop_stream_reset(), Some(REMOTE_SSRC));
+        assert!(session.pop_stream_reset().is_none());
+        assert_eq!(session.stats.current.stream_resets, 1);
+
+        // Packets buffered before the restart are discarded
+        let now = start + Duration::from_secs(1);
+        let sequence_numbers: Vec<u16> = std::iter::from_fn(|| session.pop_rtp(now, None))
+            .map(|packet| packet.get().sequence_number())
+            .collect();
+        assert_eq!(sequence_numbers, [5, 6, 7]);
+    }
+
+    #[test]
+    fn hold_is_no_stream_reset() {
+        let start = Instant::now();
+        let mut session = RtpSession::new(start, ntp_start(), SSRC, 8000);
+
+        session.recv_rtp(start, make_packet(REMOTE_SSRC, 1, 0));
+        session.recv_rtp(
+            start + Duration::from_secs(30),
+            make_packet(REMOTE_SSRC, 2, 160),
+        );
+
+        assert!(session.pop_stream_reset().is_none());
+        assert_eq!(session.receiver[0].jitter, 0.0);
+    }
+}
@@ -0,0 +1,192 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+const DEFAULT_SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+const DEFAULT_HISTORY_LENGTH: usize = 60;
+
+/// Statistics collected during a single sampling interval of an [`RtpSession`](super::RtpSession)
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct StatsSample {
+    /// Time span covered by this sample
+    pub duration: Duration,
+
+    /// Number of RTP packets sent
+    pub sent_packets: u64,
+    /// Number of RTP payload bytes sent
+    pub sent_bytes: u64,
+
+    /// Number of RTP packets received
+    pub received_packets: u64,
+    /// Number of RTP payload bytes received
+    pub received_bytes: u64,
+    /// Number of RTP packets detected as lost
+    pub lost_packets: u64,
+    /// Number of RTP packets dropped by the payload type or SSRC filter of the session
+    pub rejected_packets: u64,
+    /// Number of detected restarts of remote streams, see [`RtpSession::pop_stream_reset`](super::RtpSession::pop_stream_reset)
+    pub stream_resets: u64,
+
+    /// Highest interarrival jitter of all receivers at the end of the sample, in RTP timestamp units
+    pub jitter: f32,
+}
+
+impl StatsSample {
+    /// Outgoing payload bitrate in bits per second
+    pub fn send_bitrate(&self) -> f64 {
+        bitrate(self.sent_bytes, self.duration)
+    }
+
+    /// Incoming payload bitrate in bits per second
+    pub fn receive_bitrate(&self) -> f64 {
+        bitrate(self.received_bytes, self.duration)
+    }
+
+    /// Fraction of incoming packets lost, in the range `0.0..=1.0`
+    pub fn fraction_lost(&self) -> f64 {
+        let expected = self.received_packets + self.lost_packets;
+
+        if expected == 0 {
+            0.0
+        } else {
+            self.lost_packets as f64 / expected as f64
+        }
+    }
+}
+
+fn bitrate(bytes: u64, duration: Duration) -> f64 {
+    if duration.is_zero() {
+        0.0
+    } else {
+        (bytes * 8) as f64 / duration.as_secs_f64()
+    }
+}
+
+/// Ring buffer of [`StatsSample`]s
+#[derive(Debug)]
+pub(super) struct StatsHistory {
+    interval: Duration,
+    max_len: usize,
+
+    /// Start of the current sample
+    current_start: Option<Instant>,
+    pub(super) current: StatsSample,
+
+    samples: VecDeque<StatsSample>,
+}
+
+impl Default for StatsHistory {
+    fn default() -> Self {
+        Self::new(DEFAULT_SAMPLE_INTERVAL, DEFAULT_HISTORY_LENGTH)
+    }
+}
+
+impl StatsHistory {
+    pub(super) fn new(interval: Duration, max_len: usize) -> Self {
+        assert!(!interval.is_zero(), "sample interval must not be zero");
+
+        Self {
+            interval,
+            max_len,
+            current_start: None,
+            current: StatsSample::default(),
+            samples: VecDeque::with_capacity(max_len),
+        }
+    }
+
+    /// Finish all samples whose interval has elapsed at `now`. `jitter` is recorded into finished samples.
+    pub(super) fn advance(&mut self, now: Instant, jitter: f32) {
+        let Some(current_start) = self.current_start else {
+            self.current_start = Some(now);
+            return;
+        };
+
+        let elapsed = now.saturating_duration_since(current_start);
+
+        if elapsed < self.interval {
+            return;
+        }
+
+        let elapsed_intervals = (elapsed.as_nanos() / self.interval.as_nanos()) as usize;
+
+        self.push(StatsSample {
+            duration: self.interval,
+            jitter,
+            ..self.current
+        });
+
+        // Fill up intervals in which nothing happened, no need to push more than the history can hold
+        for _ in 1..elapsed_intervals.min(self.max_len + 1) {
+            self.push(StatsSample {
+                duration: self.interval,
+                jitter,
+                ..StatsSample::default()
+            });
+        }
+
+        self.current = StatsSample::default();
+        self.current_start = Some(current_start + self.interval * elapsed_intervals as u32);
+    }
+
+    fn push(&mut self, sample: StatsSample) {
+        if self.max_len == 0 {
+            return;
+        }
+
+        if self.samples.len() == self.max_len {
+            self.samples.pop_front();
+        }
+
+        self.samples.push_back(sample);
+    }
+
+    pub(super) fn samples(&self) -> impl ExactSizeIterator<Item = &StatsSample> + '_ {
+        self.samples.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_are_rolled_over() {
+        let start = Instant::now();
+        let mut history = StatsHistory::new(Duration::from_secs(1), 3);
+
+        history.advance(start, 0.0);
+        history.current.sent_packets = 50;
+        history.current.sent_bytes = 8000;
+
+        history.advance(start + Duration::from_millis(500), 0.0);
+        assert_eq!(history.samples().len(), 0);
+
+        history.advance(start + Duration::from_millis(1500), 2.0);
+        assert_eq!(history.samples().len(), 1);
+
+        let sample = history.samples().next().unwrap();
+        assert_eq!(sample.sent_packets, 50);
+        assert_eq!(sample.send_bitrate(), 64000.0);
+        assert_eq!(sample.jitter, 2.0);
+        assert_eq!(history.current, StatsSample::default());
+    }
+
+    #[test]
+    fn idle_intervals_are_filled_and_history_is_bounded() {
+        let start = Instant::now();
+        let mut history = StatsHistory::new(Duration::from_secs(1), 3);
+
+        history.advance(start, 0.0);
+        history.current.received_packets = 10;
+
+        history.advance(start + Duration::from_secs(2), 0.0);
+        assert_eq!(history.samples().len(), 2);
+        assert_eq!(history.samples().next().unwrap().received_packets, 10);
+        assert_eq!(history.samples().nth(1).unwrap().received_packets, 0);
+
+        history.advance(start + Duration::from_secs(100), 0.0);
+        assert_eq!(history.samples().len(), 3);
+        assert!(history.samples().all(|s| s.received_packets == 0));
+    }
+}
@@ -0,0 +1,48 @@
+use crate::{VP8PayloadDescriptor, VP8};
+use bytes::Bytes;
+use ezk_rtp::{DePayloader, FrameAssemblyResult};
+
+pub struct VP8DePayloader;
+
+impl DePayloader<VP8> for VP8DePayloader {
+    const FRAGMENTED: bool = true;
+
+    fn depayload(&mut self, payload: &[u8]) -> Bytes {
+        depayload_frame(&[payload]).unwrap_or_default()
+    }
+
+    fn depayload_fragments(
+        &mut self,
+        fragments: &[&[u8]],
+        result: FrameAssemblyResult,
+    ) -> Option<Bytes> {
+        if result != FrameAssemblyResult::Complete {
+            return None;
+        }
+
+        depayload_frame(fragments)
+    }
+}
+
+/// Strip the payload descriptors of all RTP payloads of a frame and concatenate them.
+///
+/// Returns `None` if a descriptor is malformed or the first payload doesn't start the frame.
+pub(crate) fn depayload_frame(payloads: &[&[u8]]) -> Option<Bytes> {
+    let mut frame = vec![];
+
+    for (i, payload) in payloads.iter().enumerate() {
+        let (descriptor, payload) = VP8PayloadDescriptor::parse(payload)?;
+
+        if i == 0 && !(descriptor.start_of_partition && descriptor.partition_index == 0) {
+            return None;
+        }
+
+        frame.extend_from_slice(payload);
+    }
+
+    if frame.is_empty() {
+        None
+    } else {
+        Some(Bytes::from(frame))
+    }
+}
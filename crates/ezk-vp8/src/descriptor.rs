@@ -0,0 +1,145 @@
+const X_BIT: u8 = 0b1000_0000;
+const N_BIT: u8 = 0b0010_0000;
+const S_BIT: u8 = 0b0001_0000;
+const PID_MASK: u8 = 0b0000_0111;
+
+const I_BIT: u8 = 0b1000_0000;
+const L_BIT: u8 = 0b0100_0000;
+const T_BIT: u8 = 0b0010_0000;
+const K_BIT: u8 = 0b0001_0000;
+
+const M_BIT: u8 = 0b1000_0000;
+
+/// VP8 payload descriptor, which prefixes every RTP payload
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct VP8PayloadDescriptor {
+    /// The frame can be discarded without affecting any other frames
+    pub non_reference_frame: bool,
+    /// The payload starts a new VP8 partition
+    pub start_of_partition: bool,
+    pub partition_index: u8,
+
+    /// 7 or 15 bit picture id, incremented with every frame
+    pub picture_id: Option<u16>,
+    pub tl0_pic_idx: Option<u8>,
+    /// Temporal layer index and layer sync bit
+    pub temporal_layer: Option<(u8, bool)>,
+    pub key_index: Option<u8>,
+}
+
+impl VP8PayloadDescriptor {
+    /// Parse the descriptor from the start of an RTP payload, returns the descriptor and the remaining payload
+    pub fn parse(payload: &[u8]) -> Option<(Self, &[u8])> {
+        let (&b, mut rest) = payload.split_first()?;
+
+        let mut descriptor = Self {
+            non_reference_frame: b & N_BIT != 0,
+            start_of_partition: b & S_BIT != 0,
+            partition_index: b & PID_MASK,
+            ..Self::default()
+        };
+
+        if b & X_BIT == 0 {
+            return Some((descriptor, rest));
+        }
+
+        let (&x, rest_) = rest.split_first()?;
+        rest = rest_;
+
+        if x & I_BIT != 0 {
+            let (&b, rest_) = rest.split_first()?;
+            rest = rest_;
+
+            if b & M_BIT != 0 {
+                let (&b2, rest_) = rest.split_first()?;
+                rest = rest_;
+
+                descriptor.picture_id = Some(u16::from_be_bytes([b & !M_BIT, b2]));
+            } else {
+                descriptor.picture_id = Some(u16::from(b));
+            }
+        }
+
+        if x & L_BIT != 0 {
+            let (&tl0_pic_idx, rest_) = rest.split_first()?;
+            rest = rest_;
+
+            descriptor.tl0_pic_idx = Some(tl0_pic_idx);
+        }
+
+        if x & (T_BIT | K_BIT) != 0 {
+            let (&b, rest_) = rest.split_first()?;
+            rest = rest_;
+
+            if x & T_BIT != 0 {
+                descriptor.temporal_layer = Some((b >> 6, b & 0b0010_0000 != 0));
+            }
+
+            if x & K_BIT != 0 {
+                descriptor.key_index = Some(b & 0b0001_1111);
+            }
+        }
+
+        Some((descriptor, rest))
+    }
+
+    /// Write the descriptor to `dst`, the picture id is always written using 15 bits
+    pub fn write(&self, dst: &mut Vec<u8>) {
+        let mut b = self.partition_index & PID_MASK;
+
+        if self.non_reference_frame {
+            b |= N_BIT;
+        }
+
+        if self.start_of_partition {
+            b |= S_BIT;
+        }
+
+        let mut x = 0;
+
+        if self.picture_id.is_some() {
+            x |= I_BIT;
+        }
+
+        if self.tl0_pic_idx.is_some() {
+            x |= L_BIT;
+        }
+
+        if self.temporal_layer.is_some() {
+            x |= T_BIT;
+        }
+
+        if self.key_index.is_some() {
+            x |= K_BIT;
+        }
+
+        if x == 0 {
+            dst.push(b);
+            return;
+        }
+
+        dst.push(b | X_BIT);
+        dst.push(x);
+
+        if let Some(picture_id) = self.picture_id {
+            let [b1, b2] = (picture_id & 0x7FFF).to_be_bytes();
+            dst.extend([b1 | M_BIT, b2]);
+        }
+
+        dst.extend(self.tl0_pic_idx);
+
+        if self.temporal_layer.is_some() || self.key_index.is_some() {
+            let mut b = self.key_index.unwrap_or_default() & 0b0001_1111;
+
+            if let Some((temporal_layer_index, layer_sync)) = self.temporal_layer {
+                b |= temporal_layer_index << 6;
+
+                if layer_sync {
+                    b |= 0b0010_0000;
+                }
+            }
+
+            dst.push(b);
+        }
+    }
+}
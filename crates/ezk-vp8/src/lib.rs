@@ -0,0 +1,67 @@
+//! VP8 RTP payload format (RFC 7741)
+//!
+//! Frames of the [`VP8`] media type contain a single encoded VP8 frame.
+
+use bytes::Bytes;
+use ezk::{ConfigRange, MediaType};
+use ezk_rtp::Payloadable;
+
+mod depayloader;
+mod descriptor;
+mod payloader;
+
+pub use depayloader::VP8DePayloader;
+pub use descriptor::VP8PayloadDescriptor;
+pub use payloader::VP8Payloader;
+
+#[derive(Debug)]
+pub enum VP8 {}
+
+impl MediaType for VP8 {
+    type ConfigRange = VP8ConfigRange;
+    type Config = VP8Config;
+    type FrameData = Bytes;
+}
+
+#[derive(Debug, Clone)]
+pub struct VP8ConfigRange;
+
+impl ConfigRange for VP8ConfigRange {
+    type Config = VP8Config;
+
+    fn any() -> Self {
+        Self {}
+    }
+
+    fn intersect(&self, _other: &Self) -> Option<Self> {
+        Some(Self {})
+    }
+
+    fn contains(&self, _config: &Self::Config) -> bool {
+        true
+    }
+}
+
+#[derive(Default, Debug, Clone)]
+pub struct VP8Config;
+
+impl Payloadable for VP8 {
+    type Payloader = VP8Payloader;
+    type DePayloader = VP8DePayloader;
+
+    const STATIC_PT: Option<u8> = None;
+    const RTP_CLOCK_RATE: u32 = 90000;
+
+    fn make_payloader(_: Self::Config) -> Self::Payloader {
+        VP8Payloader::new()
+    }
+
+    fn make_depayloader(_: Vec<Self::ConfigRange>) -> (Self::Config, Self::DePayloader) {
+        (Self::Config {}, VP8DePayloader {})
+    }
+}
+
+/// Returns if the encoded frame is a keyframe, using the inverse key frame flag of the frame tag
+pub fn is_keyframe(frame: &[u8]) -> bool {
+    frame.first().is_some_and(|frame_tag| frame_tag & 0x01 == 0)
+}
@@ -0,0 +1,103 @@
+use crate::{VP8PayloadDescriptor, VP8};
+use bytes::Bytes;
+use ezk::Frame;
+use ezk_rtp::Payloader;
+
+/// Size of the payload descriptor written by the payloader
+const DESCRIPTOR_SIZE: usize = 4;
+
+pub struct VP8Payloader {
+    picture_id: u16,
+}
+
+impl VP8Payloader {
+    pub(crate) fn new() -> Self {
+        Self {
+            picture_id: rand::random::<u16>() & 0x7FFF,
+        }
+    }
+}
+
+impl Payloader<VP8> for VP8Payloader {
+    fn payload(&mut self, frame: Frame<VP8>, max_size: usize) -> impl Iterator<Item = Bytes> + '_ {
+        let picture_id = self.picture_id;
+        self.picture_id = (self.picture_id + 1) & 0x7FFF;
+
+        payload_frame(frame.data(), picture_id, max_size).into_iter()
+    }
+}
+
+/// Split an encoded frame into RTP payloads of at most `max_size` bytes
+pub(crate) fn payload_frame(frame: &[u8], picture_id: u16, max_size: usize) -> Vec<Bytes> {
+    let chunk_size = max_size.saturating_sub(DESCRIPTOR_SIZE).max(1);
+
+    frame
+        .chunks(chunk_size)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let descriptor = VP8PayloadDescriptor {
+                start_of_partition: i == 0,
+                partition_index: 0,
+                picture_id: Some(picture_id),
+                ..VP8PayloadDescriptor::default()
+            };
+
+            let mut packet = Vec::with_capacity(DESCRIPTOR_SIZE + chunk.len());
+            descriptor.write(&mut packet);
+            packet.extend_from_slice(chunk);
+
+            Bytes::from(packet)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{depayloader::depayload_frame, is_keyframe};
+
+    #[test]
+    fn roundtrip() {
+        let frame: Vec<u8> = (0..3000).map(|i| (i * 2) as u8).collect();
+
+        let packets = payload_frame(&frame, 0x1234, 1200);
+        assert_eq!(packets.len(), 3);
+        assert!(packets.iter().all(|packet| packet.len() <= 1200));
+
+        let (descriptor, _) = VP8PayloadDescriptor::parse(&packets[0]).unwrap();
+        assert!(descriptor.start_of_partition);
+        assert_eq!(descriptor.picture_id, Some(0x1234));
+
+        let (descriptor, _) = VP8PayloadDescriptor::parse(&packets[1]).unwrap();
+        assert!(!descriptor.start_of_partition);
+
+        let packets: Vec<&[u8]> = packets.iter().map(|p| &p[..]).collect();
+        assert_eq!(depayload_frame(&packets).unwrap(), frame);
+        assert!(is_keyframe(&frame));
+    }
+
+    #[test]
+    fn parse_descriptor() {
+        // X, S, I (7 bit picture id), L, T, K
+        let payload = [0x90, 0xF0, 0x12, 0x34, 0b1010_0011, 0xAB];
+
+        let (descriptor, rest) = VP8PayloadDescriptor::parse(&payload).unwrap();
+        assert_eq!(
+            descriptor,
+            VP8PayloadDescriptor {
+                non_reference_frame: false,
+                start_of_partition: true,
+                partition_index: 0,
+                picture_id: Some(0x12),
+                tl0_pic_idx: Some(0x34),
+                temporal_layer: Some((2, true)),
+                key_index: Some(3),
+            }
+        );
+        assert_eq!(rest, [0xAB]);
+
+        let mut written = vec![];
+        descriptor.write(&mut written);
+        assert_eq!(VP8PayloadDescriptor::parse(&written).unwrap().0, descriptor);
+    }
+}
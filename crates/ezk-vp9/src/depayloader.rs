@@ -0,0 +1,52 @@
+use crate::{VP9PayloadDescriptor, VP9};
+use bytes::Bytes;
+use ezk_rtp::{DePayloader, FrameAssemblyResult};
+
+pub struct VP9DePayloader;
+
+impl DePayloader<VP9> for VP9DePayloader {
+    const FRAGMENTED: bool = true;
+
+    fn depayload(&mut self, payload: &[u8]) -> Bytes {
+        depayload_frame(&[payload]).unwrap_or_default()
+    }
+
+    fn depayload_fragments(
+        &mut self,
+        fragments: &[&[u8]],
+        result: FrameAssemblyResult,
+    ) -> Option<Bytes> {
+        if result != FrameAssemblyResult::Complete {
+            return None;
+        }
+
+        depayload_frame(fragments)
+    }
+}
+
+/// Strip the payload descriptors of all RTP payloads of a frame and concatenate them.
+///
+/// Returns `None` if a descriptor is malformed or the payloads don't start and end the frame.
+pub(crate) fn depayload_frame(payloads: &[&[u8]]) -> Option<Bytes> {
+    let mut frame = vec![];
+
+    for (i, payload) in payloads.iter().enumerate() {
+        let (descriptor, payload) = VP9PayloadDescriptor::parse(payload)?;
+
+        if i == 0 && !descriptor.start_of_frame {
+            return None;
+        }
+
+        if i == payloads.len() - 1 && !descriptor.end_of_frame {
+            return None;
+        }
+
+        frame.extend_from_slice(payload);
+    }
+
+    if frame.is_empty() {
+        None
+    } else {
+        Some(Bytes::from(frame))
+    }
+}
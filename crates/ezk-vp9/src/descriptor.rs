@@ -0,0 +1,211 @@
+const I_BIT: u8 = 0b1000_0000;
+const P_BIT: u8 = 0b0100_0000;
+const L_BIT: u8 = 0b0010_0000;
+const F_BIT: u8 = 0b0001_0000;
+const B_BIT: u8 = 0b0000_1000;
+const E_BIT: u8 = 0b0000_0100;
+const V_BIT: u8 = 0b0000_0010;
+const Z_BIT: u8 = 0b0000_0001;
+
+const M_BIT: u8 = 0b1000_0000;
+
+/// Maximum number of reference indices in flexible mode
+const MAX_P_DIFF: usize = 3;
+
+/// VP9 payload descriptor, which prefixes every RTP payload
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct VP9PayloadDescriptor {
+    /// The frame depends on previous frames
+    pub inter_picture_predicted: bool,
+    /// Flexible mode, the reference indices are carried in [`VP9PayloadDescriptor::reference_indices`]
+    pub flexible_mode: bool,
+    pub start_of_frame: bool,
+    pub end_of_frame: bool,
+    /// The frame is not used as reference by upper spatial layers
+    pub not_reference_for_upper_spatial_layers: bool,
+
+    /// 7 or 15 bit picture id, incremented with every frame
+    pub picture_id: Option<u16>,
+    pub layer_indices: Option<VP9LayerIndices>,
+    /// Picture id differences of the reference frames, only used in flexible mode
+    pub reference_indices: Vec<u8>,
+
+    /// Raw scalability structure, if present
+    pub scalability_structure: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct VP9LayerIndices {
+    pub temporal_id: u8,
+    pub switching_up_point: bool,
+    pub spatial_id: u8,
+    pub inter_layer_dependency: bool,
+    /// Only present in non-flexible mode
+    pub tl0_pic_idx: Option<u8>,
+}
+
+impl VP9PayloadDescriptor {
+    /// Parse the descriptor from the start of an RTP payload, returns the descriptor and the remaining payload
+    pub fn parse(payload: &[u8]) -> Option<(Self, &[u8])> {
+        let (&b, mut rest) = payload.split_first()?;
+
+        let mut descriptor = Self {
+            inter_picture_predicted: b & P_BIT != 0,
+            flexible_mode: b & F_BIT != 0,
+            start_of_frame: b & B_BIT != 0,
+            end_of_frame: b & E_BIT != 0,
+            not_reference_for_upper_spatial_layers: b & Z_BIT != 0,
+            ..Self::default()
+        };
+
+        if b & I_BIT != 0 {
+            let (&b, rest_) = rest.split_first()?;
+            rest = rest_;
+
+            if b & M_BIT != 0 {
+                let (&b2, rest_) = rest.split_first()?;
+                rest = rest_;
+
+                descriptor.picture_id = Some(u16::from_be_bytes([b & !M_BIT, b2]));
+            } else {
+                descriptor.picture_id = Some(u16::from(b));
+            }
+        }
+
+        if b & L_BIT != 0 {
+            let (&l, rest_) = rest.split_first()?;
+            rest = rest_;
+
+            let tl0_pic_idx = if descriptor.flexible_mode {
+                None
+            } else {
+                let (&tl0_pic_idx, rest_) = rest.split_first()?;
+                rest = rest_;
+                Some(tl0_pic_idx)
+            };
+
+            descriptor.layer_indices = Some(VP9LayerIndices {
+                temporal_id: l >> 5,
+                switching_up_point: l & 0b0001_0000 != 0,
+                spatial_id: (l >> 1) & 0b111,
+                inter_layer_dependency: l & 0b1 != 0,
+                tl0_pic_idx,
+            });
+        }
+
+        if descriptor.flexible_mode && descriptor.inter_picture_predicted {
+            loop {
+                let (&p_diff, rest_) = rest.split_first()?;
+                rest = rest_;
+
+                if descriptor.reference_indices.len() == MAX_P_DIFF {
+                    return None;
+                }
+
+                descriptor.reference_indices.push(p_diff >> 1);
+
+                // N bit, another P_DIFF follows
+                if p_diff & 0b1 == 0 {
+                    break;
+                }
+            }
+        }
+
+        if b & V_BIT != 0 {
+            let len = scalability_structure_len(rest)?;
+            let (scalability_structure, rest_) = rest.split_at(len);
+            rest = rest_;
+
+            descriptor.scalability_structure = Some(scalability_structure.to_vec());
+        }
+
+        Some((descriptor, rest))
+    }
+
+    /// Write the descriptor to `dst`, the picture id is always written using 15 bits
+    pub fn write(&self, dst: &mut Vec<u8>) {
+        let flags = [
+            (self.picture_id.is_some(), I_BIT),
+            (self.inter_picture_predicted, P_BIT),
+            (self.layer_indices.is_some(), L_BIT),
+            (self.flexible_mode, F_BIT),
+            (self.start_of_frame, B_BIT),
+            (self.end_of_frame, E_BIT),
+            (self.scalability_structure.is_some(), V_BIT),
+            (self.not_reference_for_upper_spatial_layers, Z_BIT),
+        ];
+
+        dst.push(
+            flags
+                .into_iter()
+                .filter(|(set, _)| *set)
+                .fold(0, |b, (_, bit)| b | bit),
+        );
+
+        if let Some(picture_id) = self.picture_id {
+            let [b1, b2] = (picture_id & 0x7FFF).to_be_bytes();
+            dst.extend([b1 | M_BIT, b2]);
+        }
+
+        if let Some(layer_indices) = &self.layer_indices {
+            let mut l =
+                (layer_indices.temporal_id << 5) | ((layer_indices.spatial_id & 0b111) << 1);
+
+            if layer_indices.switching_up_point {
+                l |= 0b0001_0000;
+            }
+
+            if layer_indices.inter_layer_dependency {
+                l |= 0b1;
+            }
+
+            dst.push(l);
+
+            if !self.flexible_mode {
+                dst.push(layer_indices.tl0_pic_idx.unwrap_or_default());
+            }
+        }
+
+        if self.flexible_mode && self.inter_picture_predicted {
+            let count = self.reference_indices.len().min(MAX_P_DIFF);
+
+            for (i, p_diff) in self.reference_indices[..count].iter().enumerate() {
+                let n = u8::from(i + 1 < count);
+                dst.push((p_diff << 1) | n);
+            }
+        }
+
+        if let Some(scalability_structure) = &self.scalability_structure {
+            dst.extend_from_slice(scalability_structure);
+        }
+    }
+}
+
+/// Returns the length of the scalability structure at the start of `data`
+fn scalability_structure_len(data: &[u8]) -> Option<usize> {
+    let &b = data.first()?;
+
+    let spatial_layers = usize::from(b >> 5) + 1;
+    let has_resolutions = b & 0b0001_0000 != 0;
+    let has_picture_groups = b & 0b0000_1000 != 0;
+
+    let mut len = 1;
+
+    if has_resolutions {
+        len += spatial_layers * 4;
+    }
+
+    if has_picture_groups {
+        let &picture_groups = data.get(len)?;
+        len += 1;
+
+        for _ in 0..picture_groups {
+            let &g = data.get(len)?;
+            let references = usize::from((g >> 2) & 0b11);
+
+            len += 1 + references;
+        }
+    }
+
+    (len <= data.len()).then_some(len)
+}
@@ -0,0 +1,84 @@
+//! VP9 RTP payload format (RFC 9628)
+//!
+//! Frames of the [`VP9`] media type contain a single encoded VP9 frame. Spatial scalability is not supported.
+
+use bytes::Bytes;
+use ezk::{ConfigRange, MediaType};
+use ezk_rtp::Payloadable;
+
+mod depayloader;
+mod descriptor;
+mod payloader;
+
+pub use depayloader::VP9DePayloader;
+pub use descriptor::{VP9LayerIndices, VP9PayloadDescriptor};
+pub use payloader::VP9Payloader;
+
+#[derive(Debug)]
+pub enum VP9 {}
+
+impl MediaType for VP9 {
+    type ConfigRange = VP9ConfigRange;
+    type Config = VP9Config;
+    type FrameData = Bytes;
+}
+
+#[derive(Debug, Clone)]
+pub struct VP9ConfigRange;
+
+impl ConfigRange for VP9ConfigRange {
+    type Config = VP9Config;
+
+    fn any() -> Self {
+        Self {}
+    }
+
+    fn intersect(&self, _other: &Self) -> Option<Self> {
+        Some(Self {})
+    }
+
+    fn contains(&self, _config: &Self::Config) -> bool {
+        true
+    }
+}
+
+#[derive(Default, Debug, Clone)]
+pub struct VP9Config;
+
+impl Payloadable for VP9 {
+    type Payloader = VP9Payloader;
+    type DePayloader = VP9DePayloader;
+
+    const STATIC_PT: Option<u8> = None;
+    const RTP_CLOCK_RATE: u32 = 90000;
+
+    fn make_payloader(_: Self::Config) -> Self::Payloader {
+        VP9Payloader::new()
+    }
+
+    fn make_depayloader(_: Vec<Self::ConfigRange>) -> (Self::Config, Self::DePayloader) {
+        (Self::Config {}, VP9DePayloader {})
+    }
+}
+
+/// Returns if the encoded frame is a keyframe, by reading the frame type from its uncompressed header
+pub fn is_keyframe(frame: &[u8]) -> bool {
+    let Some(&b) = frame.first() else {
+        return false;
+    };
+
+    // frame_marker
+    if b >> 6 != 0b10 {
+        return false;
+    }
+
+    let profile = ((b >> 4) & 0b01) << 1 | ((b >> 5) & 0b01);
+
+    // Profile 3 has an additional reserved bit
+    let shift = if profile == 3 { 2 } else { 3 };
+
+    let show_existing_frame = (b >> shift) & 0b1 != 0;
+    let frame_type = (b >> (shift - 1)) & 0b1;
+
+    !show_existing_frame && frame_type == 0
+}
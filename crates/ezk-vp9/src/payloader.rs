@@ -0,0 +1,118 @@
+use crate::{is_keyframe, VP9PayloadDescriptor, VP9};
+use bytes::Bytes;
+use ezk::Frame;
+use ezk_rtp::Payloader;
+
+/// Size of the payload descriptor written by the payloader
+const DESCRIPTOR_SIZE: usize = 3;
+
+pub struct VP9Payloader {
+    picture_id: u16,
+}
+
+impl VP9Payloader {
+    pub(crate) fn new() -> Self {
+        Self {
+            picture_id: rand::random::<u16>() & 0x7FFF,
+        }
+    }
+}
+
+impl Payloader<VP9> for VP9Payloader {
+    fn payload(&mut self, frame: Frame<VP9>, max_size: usize) -> impl Iterator<Item = Bytes> + '_ {
+        let picture_id = self.picture_id;
+        self.picture_id = (self.picture_id + 1) & 0x7FFF;
+
+        payload_frame(frame.data(), picture_id, max_size).into_iter()
+    }
+}
+
+/// Split an encoded frame into RTP payloads of at most `max_size` bytes
+pub(crate) fn payload_frame(frame: &[u8], picture_id: u16, max_size: usize) -> Vec<Bytes> {
+    let chunk_size = max_size.saturating_sub(DESCRIPTOR_SIZE).max(1);
+    let inter_picture_predicted = !is_keyframe(frame);
+
+    let chunks = frame.chunks(chunk_size);
+    let last = chunks.len().saturating_sub(1);
+
+    chunks
+        .enumerate()
+        .map(|(i, chunk)| {
+            let descriptor = VP9PayloadDescriptor {
+                inter_picture_predicted,
+                start_of_frame: i == 0,
+                end_of_frame: i == last,
+                picture_id: Some(picture_id),
+                ..VP9PayloadDescriptor::default()
+            };
+
+            let mut packet = Vec::with_capacity(DESCRIPTOR_SIZE + chunk.len());
+            descriptor.write(&mut packet);
+            packet.extend_from_slice(chunk);
+
+            Bytes::from(packet)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{depayloader::depayload_frame, VP9LayerIndices};
+
+    #[test]
+    fn roundtrip() {
+        // frame marker, profile 0, show_existing_frame = 0, frame_type = KEY_FRAME
+        let mut frame = vec![0b1000_0000];
+        frame.extend((0..3000).map(|i| i as u8));
+        assert!(is_keyframe(&frame));
+
+        let packets = payload_frame(&frame, 0x1234, 1200);
+        assert_eq!(packets.len(), 3);
+        assert!(packets.iter().all(|packet| packet.len() <= 1200));
+
+        let (descriptor, _) = VP9PayloadDescriptor::parse(&packets[0]).unwrap();
+        assert!(descriptor.start_of_frame && !descriptor.end_of_frame);
+        assert!(!descriptor.inter_picture_predicted);
+        assert_eq!(descriptor.picture_id, Some(0x1234));
+
+        let (descriptor, _) = VP9PayloadDescriptor::parse(&packets[2]).unwrap();
+        assert!(!descriptor.start_of_frame && descriptor.end_of_frame);
+
+        let packets: Vec<&[u8]> = packets.iter().map(|p| &p[..]).collect();
+        assert_eq!(depayload_frame(&packets).unwrap(), frame);
+
+        // inter frame
+        assert!(!is_keyframe(&[0b1000_0100]));
+    }
+
+    #[test]
+    fn parse_descriptor() {
+        let descriptor = VP9PayloadDescriptor {
+            inter_picture_predicted: true,
+            flexible_mode: true,
+            start_of_frame: true,
+            end_of_frame: false,
+            not_reference_for_upper_spatial_layers: false,
+            picture_id: Some(300),
+            layer_indices: Some(VP9LayerIndices {
+                temporal_id: 2,
+                switching_up_point: true,
+                spatial_id: 1,
+                inter_layer_dependency: false,
+                tl0_pic_idx: None,
+            }),
+            reference_indices: vec![1, 2],
+            // single spatial layer with resolution 640x480
+            scalability_structure: Some(vec![0b0001_0000, 0x02, 0x80, 0x01, 0xE0]),
+        };
+
+        let mut payload = vec![];
+        descriptor.write(&mut payload);
+        payload.push(0xAB);
+
+        let (parsed, rest) = VP9PayloadDescriptor::parse(&payload).unwrap();
+        assert_eq!(parsed, descriptor);
+        assert_eq!(rest, [0xAB]);
+    }
+}
@@ -1,4 +1,8 @@
-use std::{fmt::Debug, sync::Arc};
+use std::{
+    fmt::Debug,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 pub trait MediaType: Debug + 'static {
     type ConfigRange: ConfigRange<Config = Self::Config>;
@@ -20,6 +24,17 @@ pub struct Frame<M: MediaType> {
     frame: Arc<M::FrameData>,
 
     pub timestamp: u64,
+
+    /// Time the media in this frame was captured or received, used to measure latency across a pipeline.
+    ///
+    /// Nodes that create new frames from existing ones should carry it over, see [`Frame::with_capture_time`].
+    pub capture_time: Option<Instant>,
+
+    /// Marks the first frame of a talk spurt, after a period of silence which was not transmitted (e.g. detected
+    /// by voice activity detection). Signaled to the receiver using the RTP marker bit.
+    ///
+    /// Nodes that create new frames from existing ones should carry it over, see [`Frame::with_talk_spurt`].
+    pub talk_spurt: bool,
 }
 
 impl<M: MediaType> Clone for Frame<M> {
@@ -27,6 +42,8 @@ impl<M: MediaType> Clone for Frame<M> {
         Self {
             frame: self.frame.clone(),
             timestamp: self.timestamp,
+            capture_time: self.capture_time,
+            talk_spurt: self.talk_spurt,
         }
     }
 }
@@ -36,9 +53,26 @@ impl<M: MediaType> Frame<M> {
         Self {
             frame: Arc::new(data),
             timestamp,
+            capture_time: None,
+            talk_spurt: false,
         }
     }
 
+    pub fn with_capture_time(mut self, capture_time: Option<Instant>) -> Self {
+        self.capture_time = capture_time;
+        self
+    }
+
+    pub fn with_talk_spurt(mut self, talk_spurt: bool) -> Self {
+        self.talk_spurt = talk_spurt;
+        self
+    }
+
+    /// Time elapsed between the capture of the frame and `now`
+    pub fn latency(&self, now: Instant) -> Option<Duration> {
+        Some(now.saturating_duration_since(self.capture_time?))
+    }
+
     pub fn data(&self) -> &M::FrameData {
         &self.frame
     }
@@ -0,0 +1,226 @@
+use crate::{MediaType, NextEventIsCancelSafe, Result, Source, SourceEvent};
+use parking_lot::Mutex;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+const DEFAULT_BUCKET_WIDTH: Duration = Duration::from_millis(1);
+const DEFAULT_BUCKET_COUNT: usize = 1000;
+
+/// Records the latency of all frames passing through, measured from their [`capture_time`](crate::Frame::capture_time)
+///
+/// Place multiple probes along a pipeline (e.g. after encoding, packetizing and decoding) to find out where delay
+/// accumulates. Frames without a capture time are not recorded.
+pub struct LatencyProbe<S> {
+    source: S,
+    histogram: Arc<Mutex<LatencyHistogram>>,
+}
+
+/// Handle to read the histogram of a [`LatencyProbe`]
+#[derive(Clone)]
+pub struct LatencyProbeHandle {
+    histogram: Arc<Mutex<LatencyHistogram>>,
+}
+
+impl LatencyProbeHandle {
+    /// Returns a snapshot of the recorded latencies
+    pub fn histogram(&self) -> LatencyHistogram {
+        self.histogram.lock().clone()
+    }
+
+    /// Discard all recorded latencies
+    pub fn reset(&self) {
+        self.histogram.lock().reset();
+    }
+}
+
+impl<S: Source> LatencyProbe<S> {
+    /// Create a probe with 1000 buckets of 1ms each
+    pub fn new(source: S) -> (Self, LatencyProbeHandle) {
+        Self::with_buckets(source, DEFAULT_BUCKET_WIDTH, DEFAULT_BUCKET_COUNT)
+    }
+
+    /// Create a probe with the given histogram buckets, latencies exceeding the last bucket are recorded in it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bucket_width` is zero or `bucket_count` is zero
+    pub fn with_buckets(
+        source: S,
+        bucket_width: Duration,
+        bucket_count: usize,
+    ) -> (Self, LatencyProbeHandle) {
+        let histogram = Arc::new(Mutex::new(LatencyHistogram::new(
+            bucket_width,
+            bucket_count,
+        )));
+
+        let this = Self {
+            source,
+            histogram: histogram.clone(),
+        };
+
+        (this, LatencyProbeHandle { histogram })
+    }
+}
+
+impl<S: Source + NextEventIsCancelSafe> NextEventIsCancelSafe for LatencyProbe<S> {}
+
+impl<S: Source> Source for LatencyProbe<S> {
+    type MediaType = S::MediaType;
+
+    async fn capabilities(&mut self) -> Result<Vec<<Self::MediaType as MediaType>::ConfigRange>> {
+        self.source.capabilities().await
+    }
+
+    async fn negotiate_config(
+        &mut self,
+        available: Vec<<Self::MediaType as MediaType>::ConfigRange>,
+    ) -> Result<<Self::MediaType as MediaType>::Config> {
+        self.source.negotiate_config(available).await
+    }
+
+    async fn next_event(&mut self) -> Result<SourceEvent<Self::MediaType>> {
+        let event = self.source.next_event().await?;
+
+        if let SourceEvent::Frame(frame) = &event {
+            if let Some(latency) = frame.latency(Instant::now()) {
+                self.histogram.lock().record(latency);
+            }
+        }
+
+        Ok(event)
+    }
+}
+
+/// Histogram of latencies using buckets of a fixed width
+#[derive(Debug, Clone)]
+pub struct LatencyHistogram {
+    bucket_width: Duration,
+    buckets: Vec<u64>,
+
+    count: u64,
+    sum: Duration,
+    min: Option<Duration>,
+    max: Option<Duration>,
+}
+
+impl LatencyHistogram {
+    fn new(bucket_width: Duration, bucket_count: usize) -> Self {
+        assert!(!bucket_width.is_zero());
+        assert!(bucket_count > 0);
+
+        Self {
+            bucket_width,
+            buckets: vec![0; bucket_count],
+            count: 0,
+            sum: Duration::ZERO,
+            min: None,
+            max: None,
+        }
+    }
+
+    fn record(&mut self, latency: Duration) {
+        let index = (latency.as_nanos() / self.bucket_width.as_nanos()) as usize;
+        let index = index.min(self.buckets.len() - 1);
+
+        self.buckets[index] += 1;
+        self.count += 1;
+        self.sum += latency;
+        self.min = Some(self.min.map_or(latency, |min| min.min(latency)));
+        self.max = Some(self.max.map_or(latency, |max| max.max(latency)));
+    }
+
+    fn reset(&mut self) {
+        self.buckets.fill(0);
+        self.count = 0;
+        self.sum = Duration::ZERO;
+        self.min = None;
+        self.max = None;
+    }
+
+    /// Width of a single bucket, bucket `i` counts latencies in `i * width..(i + 1) * width`
+    pub fn bucket_width(&self) -> Duration {
+        self.bucket_width
+    }
+
+    /// Number of recorded latencies per bucket
+    pub fn buckets(&self) -> &[u64] {
+        &self.buckets
+    }
+
+    /// Total number of recorded latencies
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn min(&self) -> Option<Duration> {
+        self.min
+    }
+
+    pub fn max(&self) -> Option<Duration> {
+        self.max
+    }
+
+    pub fn mean(&self) -> Option<Duration> {
+        if self.count == 0 {
+            return None;
+        }
+
+        Some(self.sum.div_f64(self.count as f64))
+    }
+
+    /// Returns the upper bound of the bucket containing the given percentile (`0.0..=1.0`)
+    ///
+    /// The last bucket also holds all latencies exceeding it, for it the maximum recorded latency is returned.
+    pub fn percentile(&self, percentile: f64) -> Option<Duration> {
+        if self.count == 0 {
+            return None;
+        }
+
+        let target = ((self.count as f64 * percentile.clamp(0.0, 1.0)).ceil() as u64).max(1);
+        let mut seen = 0;
+
+        for (i, count) in self.buckets.iter().enumerate() {
+            seen += count;
+
+            if seen >= target {
+                if i == self.buckets.len() - 1 {
+                    return self.max;
+                }
+
+                return Some(self.bucket_width * (i as u32 + 1));
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn histogram() {
+        let mut histogram = LatencyHistogram::new(Duration::from_millis(10), 10);
+
+        for ms in [1, 5, 15, 25, 500] {
+            histogram.record(Duration::from_millis(ms));
+        }
+
+        assert_eq!(histogram.buckets(), [2, 1, 1, 0, 0, 0, 0, 0, 0, 1]);
+        assert_eq!(histogram.count(), 5);
+        assert_eq!(histogram.min(), Some(Duration::from_millis(1)));
+        assert_eq!(histogram.max(), Some(Duration::from_millis(500)));
+        assert_eq!(histogram.mean(), Some(Duration::from_micros(109_200)));
+        assert_eq!(histogram.percentile(0.5), Some(Duration::from_millis(20)));
+        assert_eq!(histogram.percentile(0.8), Some(Duration::from_millis(30)));
+        assert_eq!(histogram.percentile(1.0), Some(Duration::from_millis(500)));
+
+        histogram.reset();
+        assert_eq!(histogram.count(), 0);
+        assert_eq!(histogram.percentile(0.5), None);
+    }
+}
@@ -1,7 +1,9 @@
 mod access;
 mod config_filter;
+mod latency_probe;
 mod tasked;
 
 pub use access::{Access, AccessHandle};
 pub use config_filter::ConfigFilter;
+pub use latency_probe::{LatencyHistogram, LatencyProbe, LatencyProbeHandle};
 pub use tasked::Tasked;
@@ -1,10 +1,14 @@
 use std::alloc::Layout;
+use std::fmt;
 use std::future::Future;
-use std::mem::size_of;
 use std::pin::Pin;
 use std::ptr::{drop_in_place, NonNull};
 use std::task::{Context, Poll};
 
+/// Memory buffer which can be reused to store futures of different types and layouts.
+///
+/// Only a single future can be stored at a time. Storing a future borrows the box mutably until the returned
+/// future is dropped, so the buffer never changes while a future lives inside it.
 #[derive(Default)]
 pub struct ReusableBox {
     // using vec as convenient memory allocator
@@ -16,41 +20,183 @@ impl ReusableBox {
         Self { buffer: Vec::new() }
     }
 
+    /// Create a box which can store futures of up to `capacity` bytes (including alignment padding) without
+    /// allocating
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            buffer: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Number of bytes that can be used without reallocating
+    pub fn capacity(&self) -> usize {
+        self.buffer.capacity()
+    }
+
+    /// Store the future in the box, growing the buffer if it is too small
     pub fn store_future<'a, F, O>(&'a mut self, f: F) -> ReusedBoxFuture<'a, O>
     where
         F: Future<Output = O> + Send + 'a,
     {
-        const USIZE_SIZE: usize = size_of::<usize>();
+        self.reserve(Layout::new::<F>());
 
-        let layout = Layout::new::<F>();
+        match self.try_store_future(f) {
+            Ok(f) => f,
+            Err(_) => unreachable!("buffer must have enough capacity after reserving"),
+        }
+    }
 
-        // Make sure the buffer has the required size (+ size of usize for potential alignment)
-        self.buffer.reserve(layout.size() + USIZE_SIZE);
+    /// Store the future in the box without reallocating.
+    ///
+    /// Returns the future inside the error if the buffer's capacity is insufficient.
+    pub fn try_store_future<'a, F, O>(
+        &'a mut self,
+        f: F,
+    ) -> Result<ReusedBoxFuture<'a, O>, TryStoreError<F>>
+    where
+        F: Future<Output = O> + Send + 'a,
+    {
+        let ptr = self.try_store(f)?;
 
-        let align_offset = self.buffer.as_ptr().align_offset(layout.align());
+        Ok(ReusedBoxFuture {
+            ptr_into_buffer: ptr,
+        })
+    }
 
-        assert!(
-            align_offset <= USIZE_SIZE,
-            "Didn't expect the offset to be larger than {USIZE_SIZE} (is {align_offset})"
-        );
+    /// Store the `!Send` future in the box, growing the buffer if it is too small
+    pub fn store_local_future<'a, F, O>(&'a mut self, f: F) -> ReusedLocalBoxFuture<'a, O>
+    where
+        F: Future<Output = O> + 'a,
+    {
+        self.reserve(Layout::new::<F>());
 
-        unsafe {
-            let ptr = self.buffer.as_mut_ptr().add(align_offset).cast::<F>();
+        match self.try_store_local_future(f) {
+            Ok(f) => f,
+            Err(_) => unreachable!("buffer must have enough capacity after reserving"),
+        }
+    }
+
+    /// Store the `!Send` future in the box without reallocating.
+    ///
+    /// Returns the future inside the error if the buffer's capacity is insufficient.
+    pub fn try_store_local_future<'a, F, O>(
+        &'a mut self,
+        f: F,
+    ) -> Result<ReusedLocalBoxFuture<'a, O>, TryStoreError<F>>
+    where
+        F: Future<Output = O> + 'a,
+    {
+        let ptr = self.try_store(f)?;
 
-            ptr.write(f);
+        Ok(ReusedLocalBoxFuture {
+            ptr_into_buffer: ptr,
+        })
+    }
 
-            // Cast ptr to dyn Future which can be used later to access and drop the future without any generic parameters
-            let ptr = NonNull::new_unchecked(ptr as *mut (dyn Future<Output = O> + Send + 'a));
+    /// Make sure the buffer can hold a value with the given layout at any alignment offset
+    fn reserve(&mut self, layout: Layout) {
+        if layout.size() == 0 {
+            return;
+        }
 
-            ReusedBoxFuture {
-                ptr_into_buffer: ptr,
-            }
+        // Reserving relative to len, which is always 0
+        self.buffer.reserve(required_capacity(layout));
+    }
+
+    /// Write `value` into the buffer and return a pointer to it
+    fn try_store<T>(&mut self, value: T) -> Result<NonNull<T>, TryStoreError<T>> {
+        let layout = Layout::new::<T>();
+
+        if layout.size() == 0 {
+            // Zero sized types don't need any memory, just a well aligned pointer
+            let ptr = NonNull::<T>::dangling();
+
+            // SAFETY: writing a ZST to a dangling but aligned pointer is valid
+            unsafe { ptr.as_ptr().write(value) };
+
+            return Ok(ptr);
         }
+
+        let buffer_ptr = self.buffer.as_mut_ptr();
+        let align_offset = buffer_ptr.align_offset(layout.align());
+
+        let fits = align_offset
+            .checked_add(layout.size())
+            .is_some_and(|end| end <= self.buffer.capacity());
+
+        if !fits {
+            return Err(TryStoreError {
+                value,
+                required: required_capacity(layout),
+                capacity: self.buffer.capacity(),
+            });
+        }
+
+        // SAFETY:
+        // The memory range [align_offset, align_offset + size) was checked to be inside the vec's allocation
+        // and is properly aligned for T. The vec is never modified while the returned pointer is in use, since
+        // the returned futures borrow the ReusableBox mutably.
+        unsafe {
+            let ptr = buffer_ptr.add(align_offset).cast::<T>();
+            ptr.write(value);
+
+            Ok(NonNull::new_unchecked(ptr))
+        }
+    }
+}
+
+/// Capacity required to store a value with the given layout regardless of the buffer's alignment
+fn required_capacity(layout: Layout) -> usize {
+    layout.size() + layout.align() - 1
+}
+
+/// Error returned when trying to store a future in a [`ReusableBox`] that doesn't have enough capacity
+pub struct TryStoreError<F> {
+    value: F,
+    required: usize,
+    capacity: usize,
+}
+
+impl<F> TryStoreError<F> {
+    /// Returns the future that could not be stored
+    pub fn into_inner(self) -> F {
+        self.value
+    }
+
+    /// Capacity in bytes required to store the future
+    pub fn required(&self) -> usize {
+        self.required
+    }
+
+    /// Capacity in bytes of the box
+    pub fn capacity(&self) -> usize {
+        self.capacity
     }
 }
 
+impl<F> fmt::Debug for TryStoreError<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TryStoreError")
+            .field("required", &self.required)
+            .field("capacity", &self.capacity)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<F> fmt::Display for TryStoreError<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "ReusableBox requires a capacity of {} bytes, but only has {}",
+            self.required, self.capacity
+        )
+    }
+}
+
+impl<F> std::error::Error for TryStoreError<F> {}
+
 pub struct ReusedBoxFuture<'a, O> {
-    ptr_into_buffer: NonNull<(dyn Future<Output = O> + Send + 'a)>,
+    ptr_into_buffer: NonNull<dyn Future<Output = O> + Send + 'a>,
 }
 
 // SAFETY:
@@ -86,6 +232,35 @@ impl<O> Drop for ReusedBoxFuture<'_, O> {
     }
 }
 
+/// Like [`ReusedBoxFuture`] but for futures which are not `Send`
+pub struct ReusedLocalBoxFuture<'a, O> {
+    ptr_into_buffer: NonNull<dyn Future<Output = O> + 'a>,
+}
+
+impl<'a, O> ReusedLocalBoxFuture<'a, O> {
+    fn future(&mut self) -> Pin<&mut (dyn Future<Output = O> + 'a)> {
+        // SAFETY: see ReusedBoxFuture::future
+        unsafe { Pin::new_unchecked(self.ptr_into_buffer.as_mut()) }
+    }
+}
+
+impl<O> Future for ReusedLocalBoxFuture<'_, O> {
+    type Output = O;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.future().poll(cx)
+    }
+}
+
+impl<O> Drop for ReusedLocalBoxFuture<'_, O> {
+    fn drop(&mut self) {
+        // SAFETY: see ReusedBoxFuture's drop
+        unsafe {
+            drop_in_place(self.ptr_into_buffer.as_ptr());
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -204,4 +379,165 @@ mod tests {
 
         assert_eq!(v, 3);
     }
+
+    #[repr(align(256))]
+    struct OverAligned(u8);
+
+    #[tokio::test]
+    async fn over_aligned_future() {
+        let mut holder = ReusableBox::new();
+
+        for i in 0..10 {
+            let value = OverAligned(i);
+            let g = holder.store_future(async move {
+                assert_eq!(&value as *const OverAligned as usize % 256, 0);
+                value.0
+            });
+
+            assert_eq!(g.await, i);
+        }
+    }
+
+    #[tokio::test]
+    async fn grow_for_larger_future() {
+        let mut holder = ReusableBox::new();
+
+        holder.store_future(async { 1u8 }).await;
+        let small_capacity = holder.capacity();
+
+        let large = [7u8; 1024];
+        let v = holder.store_future(async move { large.iter().map(|&v| v as u32).sum::<u32>() });
+
+        assert_eq!(v.await, 7 * 1024);
+        assert!(holder.capacity() > small_capacity);
+    }
+
+    #[tokio::test]
+    async fn try_store_without_capacity() {
+        let mut holder = ReusableBox::new();
+
+        let large = [1u8; 64];
+        let err = match holder.try_store_future(async move { large.len() }) {
+            Ok(_) => panic!("empty box must not be able to store future"),
+            Err(e) => e,
+        };
+
+        assert_eq!(err.capacity(), 0);
+        assert!(err.required() >= 64);
+
+        // The future can be retrieved from the error and stored after allocating
+        let mut holder = ReusableBox::with_capacity(err.required());
+        let f = err.into_inner();
+
+        match holder.try_store_future(f) {
+            Ok(f) => assert_eq!(f.await, 64),
+            Err(_) => panic!("box must have enough capacity"),
+        };
+    }
+
+    #[tokio::test]
+    async fn zero_sized_future() {
+        struct Zst;
+
+        impl Future for Zst {
+            type Output = u32;
+
+            fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
+                Poll::Ready(1)
+            }
+        }
+
+        let mut holder = ReusableBox::new();
+
+        match holder.try_store_future(Zst) {
+            Ok(f) => assert_eq!(f.await, 1),
+            Err(_) => panic!("zero sized future must not require any capacity"),
+        };
+
+        assert_eq!(holder.capacity(), 0);
+    }
+
+    #[tokio::test]
+    async fn local_future() {
+        use std::rc::Rc;
+
+        let mut holder = ReusableBox::new();
+
+        let rc = Rc::new(5);
+
+        for i in 0..3 {
+            let rc = rc.clone();
+            let g = holder.store_local_future(async move { *rc + i });
+
+            assert_eq!(g.await, 5 + i);
+        }
+
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+
+    #[tokio::test]
+    async fn reuse_with_different_layouts() {
+        use std::sync::atomic::{AtomicU32, Ordering};
+
+        static DROPS: AtomicU32 = AtomicU32::new(0);
+
+        struct DroppedZst;
+
+        impl Future for DroppedZst {
+            type Output = ();
+
+            fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
+                Poll::Ready(())
+            }
+        }
+
+        impl Drop for DroppedZst {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let over_aligned = |i| {
+            let value = OverAligned(i);
+
+            async move {
+                assert_eq!(&value as *const OverAligned as usize % 256, 0);
+                value.0
+            }
+        };
+
+        let mut holder = ReusableBox::new();
+
+        assert_eq!(holder.store_future(over_aligned(1)).await, 1);
+        let capacity = holder.capacity();
+
+        // Smaller and differently aligned futures reuse the over-aligned allocation
+        assert_eq!(holder.store_future(async { 2u64 }).await, 2);
+        assert_eq!(holder.capacity(), capacity);
+
+        holder.store_local_future(DroppedZst).await;
+        assert_eq!(DROPS.load(Ordering::Relaxed), 1);
+
+        assert_eq!(holder.store_future(over_aligned(3)).await, 3);
+        assert_eq!(holder.capacity(), capacity);
+    }
+
+    #[tokio::test]
+    async fn self_referential_future() {
+        let mut holder = ReusableBox::new();
+
+        for i in 0..3u8 {
+            let g = holder.store_future(async move {
+                let values = [i; 16];
+                let first = &values[0];
+
+                // The reference into the future's own state must stay valid across polls
+                tokio::task::yield_now().await;
+
+                *first
+            });
+
+            assert_eq!(g.await, i);
+        }
+    }
 }